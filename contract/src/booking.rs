@@ -0,0 +1,187 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{near_bindgen, AccountId};
+
+use crate::*;
+
+pub(crate) type BookingId = String;
+
+// 予約の状態
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum BookingStatus {
+    // チェックイン前
+    Booked,
+    // 滞在中
+    CheckedIn,
+    // チェックアウト済み
+    CheckedOut,
+    // 宿泊者またはオーナーがキャンセルした
+    Cancelled,
+    // チェックインされないまま部屋が削除された
+    NoShow,
+}
+
+// 予約の記録
+// // チェックアウトやキャンセルの後も、予約の履歴として残る
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Booking {
+    pub(crate) room_id: RoomId,
+    pub(crate) guest_id: AccountId,
+    pub(crate) check_in_date: CheckInDate,
+    pub(crate) check_out_date: CheckOutDate,
+    // 宿泊する人数
+    pub(crate) guests: u8,
+    pub(crate) amount_paid: U128,
+    // 予約した日時（ブロックのタイムスタンプ、ナノ秒）
+    pub(crate) created_at: U64,
+    pub(crate) status: BookingStatus,
+}
+
+impl Booking {
+    pub(crate) fn new(
+        room_id: RoomId,
+        guest_id: AccountId,
+        check_in_date: CheckInDate,
+        check_out_date: CheckOutDate,
+        guests: u8,
+        amount_paid: U128,
+        created_at: u64,
+        status: BookingStatus,
+    ) -> Self {
+        Self {
+            room_id,
+            guest_id,
+            check_in_date,
+            check_out_date,
+            guests,
+            amount_paid,
+            created_at: U64(created_at),
+            status,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // 予約の記録を取得する
+    // // 存在しない場合は`null`が返る
+    pub fn get_booking(&self, booking_id: BookingId) -> Option<Booking> {
+        self.bookings_by_id.get(&booking_id)
+    }
+}
+
+impl Contract {
+    // 予約のIDを連番で発行し、予約の記録を保存する
+    pub(crate) fn add_booking(&mut self, booking: Booking) -> BookingId {
+        let booking_id = self.next_booking_id.to_string();
+        self.next_booking_id += 1;
+        self.bookings_by_id.insert(&booking_id, &booking);
+        booking_id
+    }
+
+    pub(crate) fn set_booking_status(&mut self, booking_id: &BookingId, status: BookingStatus) {
+        let mut booking = self
+            .bookings_by_id
+            .get(booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        booking.status = status;
+        self.bookings_by_id.insert(booking_id, &booking);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    // オーナーの`bob`が1泊1NEARの部屋を登録する
+    fn setup(context: &mut VMContextBuilder) -> (Contract, RoomId) {
+        context.block_timestamp(1_000);
+        let mut contract = new_contract(context);
+        let room_id = add_room(&mut contract, "101", ONE_NEAR);
+        (contract, room_id)
+    }
+
+    // `charlie`が`check_in_date`から1泊予約する
+    fn book_night(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        room_id: &RoomId,
+        check_in_date: &str,
+    ) -> BookingId {
+        book(
+            context,
+            contract,
+            room_id,
+            accounts(2),
+            check_in_date,
+            ONE_NEAR,
+        )
+    }
+
+    fn status_of(contract: &Contract, booking_id: &BookingId) -> BookingStatus {
+        contract.get_booking(booking_id.clone()).unwrap().status
+    }
+
+    #[test]
+    fn booking_status_follows_lifecycle() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+
+        let booking_id = book_night(&mut context, &mut contract, &room_id, "1970-01-02");
+        assert_eq!(booking_id, "0");
+        assert_eq!(
+            contract.get_booking(booking_id.clone()),
+            Some(Booking {
+                room_id: room_id.clone(),
+                guest_id: accounts(2),
+                check_in_date: date("1970-01-02"),
+                check_out_date: date("1970-01-03"),
+                guests: 1,
+                amount_paid: U128(ONE_NEAR),
+                created_at: U64(1_000),
+                status: BookingStatus::Booked,
+            })
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("1970-01-02"))
+            .build());
+        contract.change_status_to_stay(room_id.clone(), date("1970-01-02"), accounts(2));
+        assert_eq!(status_of(&contract, &booking_id), BookingStatus::CheckedIn);
+        contract.change_status_to_available(room_id.clone(), date("1970-01-02"), accounts(2));
+        assert_eq!(status_of(&contract, &booking_id), BookingStatus::CheckedOut);
+
+        // 同じ日付に再び予約すると、新しいIDが発行される
+        let booking_id = book_night(&mut context, &mut contract, &room_id, "1970-01-02");
+        assert_eq!(booking_id, "1");
+        testing_env!(context.attached_deposit(0).build());
+        contract.cancel_booking(room_id, date("1970-01-02"));
+        assert_eq!(status_of(&contract, &booking_id), BookingStatus::Cancelled);
+        assert_eq!(contract.get_booking("2".to_string()), None);
+    }
+
+    #[test]
+    fn unchecked_booking_is_no_show_after_room_removal() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        let booking_id = book_night(&mut context, &mut contract, &room_id, "1970-01-02");
+
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("1970-01-04"))
+            .build());
+        contract.remove_room(room_id);
+        assert_eq!(status_of(&contract, &booking_id), BookingStatus::NoShow);
+    }
+}
@@ -0,0 +1,218 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::de::{self, Deserializer};
+use near_sdk::serde::{Deserialize, Serialize, Serializer};
+
+use std::fmt;
+use std::str::FromStr;
+
+const NANOSECONDS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const MILLISECONDS_PER_DAY: i64 = 24 * 60 * 60 * 1_000;
+
+// 暦の上の日付（ISO-8601の`YYYY-MM-DD`形式）
+// // 内部では1970-01-01からの経過日数として保持するため、同じ日付は必ず同じ値になる
+#[derive(
+    BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Date {
+    days: i64,
+}
+
+impl Date {
+    // 年月日から日付を作成する
+    // // 存在しない日付（2月30日など）の場合は`None`を返す
+    pub fn from_ymd(year: i64, month: u32, day: u32) -> Option<Self> {
+        if !(0..=9999).contains(&year) || !(1..=12).contains(&month) {
+            return None;
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Self {
+            days: days_from_civil(year, month as i64, day as i64),
+        })
+    }
+
+    // タイムスタンプ（1970-01-01 00:00 UTCからの経過ナノ秒）が属する日付を作成する
+    pub fn from_timestamp(timestamp: u64) -> Self {
+        Self {
+            days: (timestamp / NANOSECONDS_PER_DAY) as i64,
+        }
+    }
+
+    // その日の0時（UTC）の、1970-01-01 00:00 UTCからの経過ミリ秒を返す
+    pub fn timestamp_ms(&self) -> i64 {
+        self.days * MILLISECONDS_PER_DAY
+    }
+
+    // 年月日に変換する
+    pub fn ymd(&self) -> (i64, u32, u32) {
+        civil_from_days(self.days)
+    }
+
+    // `other`までの日数を返す
+    pub fn days_until(&self, other: Date) -> i64 {
+        other.days - self.days
+    }
+
+    // 曜日を返す（月曜日が0、日曜日が6）
+    // // 1970-01-01は木曜日
+    pub fn weekday(&self) -> u32 {
+        (self.days + 3).rem_euclid(7) as u32
+    }
+
+    // `days`日後の日付を返す
+    pub fn add_days(&self, days: i64) -> Date {
+        Self {
+            days: self.days + days,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.ymd();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    // `YYYY-MM-DD`形式のみを受け付ける
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("ERR_INVALID_DATE: expected YYYY-MM-DD, got {:?}", s);
+
+        let bytes = s.as_bytes();
+        if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return Err(err());
+        }
+        let number = |range: std::ops::Range<usize>| -> Result<u32, String> {
+            let part = &s[range];
+            if !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(err());
+            }
+            part.parse().map_err(|_| err())
+        };
+        let year = number(0..4)?;
+        let month = number(5..7)?;
+        let day = number(8..10)?;
+
+        Date::from_ymd(year as i64, month, day).ok_or_else(err)
+    }
+}
+
+// JSONでは`YYYY-MM-DD`形式の文字列として扱う
+impl Serialize for Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// 年月日を1970-01-01からの経過日数に変換する
+// // 3月始まりの暦に置き換えて計算する
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// 1970-01-01からの経過日数を年月日に変換する
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::serde_json;
+
+    #[test]
+    fn parse_then_format() {
+        let date: Date = "2222-01-01".parse().unwrap();
+        assert_eq!(date.ymd(), (2222, 1, 1));
+        assert_eq!(date.to_string(), "2222-01-01");
+
+        // うるう年の2月29日
+        let date: Date = "2224-02-29".parse().unwrap();
+        assert_eq!(date.to_string(), "2224-02-29");
+
+        let epoch: Date = "1970-01-01".parse().unwrap();
+        assert_eq!(epoch.weekday(), 3);
+        // 2222-01-01は火曜日
+        assert_eq!("2222-01-01".parse::<Date>().unwrap().weekday(), 1);
+        assert_eq!(epoch.days_until(date), 92_830);
+        assert_eq!(Date::from_timestamp(0), epoch);
+        assert_eq!(date.timestamp_ms(), 8_020_512_000_000);
+        // 2222-01-01 23:59:59 UTC
+        let date = Date::from_timestamp(7_952_428_799_000_000_000);
+        assert_eq!(date.to_string(), "2222-01-01");
+    }
+
+    #[test]
+    fn reject_invalid_dates() {
+        for s in [
+            "2222-1-1",
+            "tomorrow",
+            "",
+            "2222-13-01",
+            "2222-00-10",
+            "2222-02-30",
+            "2223-02-29",
+            "2222-01-01T00:00:00Z",
+            "+222-01-01",
+        ] {
+            assert!(s.parse::<Date>().is_err(), "{} should be rejected", s);
+        }
+    }
+
+    #[test]
+    fn serialize_canonically() {
+        let date: Date = "2222-12-31".parse().unwrap();
+
+        let json = serde_json::to_string(&date).unwrap();
+        assert_eq!(json, "\"2222-12-31\"");
+        assert_eq!(serde_json::from_str::<Date>(&json).unwrap(), date);
+        assert!(serde_json::from_str::<Date>("\"2222-12-32\"").is_err());
+
+        let bytes = date.try_to_vec().unwrap();
+        assert_eq!(Date::try_from_slice(&bytes).unwrap(), date);
+    }
+}
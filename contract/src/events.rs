@@ -0,0 +1,200 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId};
+
+use crate::Date;
+
+// インデクサーが識別するためのイベントの規格名とバージョン
+const EVENT_STANDARD: &str = "hotel_booking";
+const EVENT_VERSION: &str = "1.0.0";
+
+// NEP-171: 予約のNFTのイベントの規格名とバージョン
+const NFT_EVENT_STANDARD: &str = "nep171";
+const NFT_EVENT_VERSION: &str = "1.0.0";
+
+// コントラクトが発行するイベント
+// // `EVENT_JSON:{"standard": ..., "version": ..., "event": ..., "data": [...]}`の形式でログに出力される
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum HotelEvent<'a> {
+    RoomCreated(Vec<RoomCreatedData<'a>>),
+    RoomUpdated(Vec<RoomUpdatedData<'a>>),
+    RoomRemoved(Vec<RoomRemovedData<'a>>),
+    BookingCreated(Vec<BookingCreatedData<'a>>),
+    CheckedIn(Vec<BookingData<'a>>),
+    CheckedOut(Vec<BookingData<'a>>),
+    Cancelled(Vec<CancelledData<'a>>),
+    Refunded(Vec<RefundedData<'a>>),
+    BookingResold(Vec<BookingResoldData<'a>>),
+}
+
+// 部屋が登録された時のイベント
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoomCreatedData<'a> {
+    pub room_id: &'a str,
+    pub owner_id: &'a AccountId,
+    pub name: &'a str,
+    pub location: &'a str,
+    pub price: U128,
+}
+
+// 部屋の情報が更新された時のイベント
+// // 変更された項目のみを出力する
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoomUpdatedData<'a> {
+    pub room_id: &'a str,
+    pub owner_id: &'a AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub beds: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<U128>,
+}
+
+// 部屋が削除された時のイベント
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoomRemovedData<'a> {
+    pub room_id: &'a str,
+    pub owner_id: &'a AccountId,
+}
+
+// 部屋が予約された時のイベント
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BookingCreatedData<'a> {
+    pub booking_id: &'a str,
+    pub room_id: &'a str,
+    pub guest_id: &'a AccountId,
+    pub check_in_date: Date,
+    pub check_out_date: Date,
+    pub amount_paid: U128,
+}
+
+// 宿泊者がチェックイン、チェックアウトした時のイベント
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BookingData<'a> {
+    pub booking_id: &'a str,
+    pub room_id: &'a str,
+    pub guest_id: &'a AccountId,
+    pub check_in_date: Date,
+}
+
+// 予約がキャンセルされた時のイベント
+// // `cancelled_by`は宿泊者またはオーナーのアカウントID
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CancelledData<'a> {
+    pub booking_id: &'a str,
+    pub room_id: &'a str,
+    pub guest_id: &'a AccountId,
+    pub check_in_date: Date,
+    pub cancelled_by: &'a AccountId,
+}
+
+// 宿泊者に返金した時のイベント
+// // キャンセル時の返金と、予約時に多く送付された分の返金がある
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundedData<'a> {
+    pub booking_id: &'a str,
+    pub room_id: &'a str,
+    pub guest_id: &'a AccountId,
+    pub check_in_date: Date,
+    pub amount: U128,
+}
+
+// NEP-171: 予約のNFTが発行、移転、焼却された時のイベント
+// // ウォレットやインデクサーが、アカウントが保有するNFTを把握するために使用する
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+pub enum NftEvent<'a> {
+    #[serde(rename = "nft_mint")]
+    Mint(Vec<NftMintData<'a>>),
+    #[serde(rename = "nft_transfer")]
+    Transfer(Vec<NftTransferData<'a>>),
+    #[serde(rename = "nft_burn")]
+    Burn(Vec<NftBurnData<'a>>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftMintData<'a> {
+    pub owner_id: &'a AccountId,
+    pub token_ids: Vec<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftTransferData<'a> {
+    pub old_owner_id: &'a AccountId,
+    pub new_owner_id: &'a AccountId,
+    pub token_ids: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftBurnData<'a> {
+    pub owner_id: &'a AccountId,
+    pub token_ids: Vec<&'a str>,
+}
+
+// 転売に出された予約が購入された時のイベント
+// // `royalty`は転売価格のうちオーナーが受け取った金額
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BookingResoldData<'a> {
+    pub booking_id: &'a str,
+    pub room_id: &'a str,
+    pub seller_id: &'a AccountId,
+    pub buyer_id: &'a AccountId,
+    pub price: U128,
+    pub royalty: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a, T: Serialize> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a T,
+}
+
+// イベントをログに出力する
+fn emit_event<T: Serialize>(standard: &'static str, version: &'static str, event: &T) {
+    let log = EventLog {
+        standard,
+        version,
+        event,
+    };
+    env::log_str(&format!(
+        "EVENT_JSON:{}",
+        serde_json::to_string(&log).expect("ERR_SERIALIZE_EVENT")
+    ));
+}
+
+impl HotelEvent<'_> {
+    pub fn emit(&self) {
+        emit_event(EVENT_STANDARD, EVENT_VERSION, self);
+    }
+}
+
+impl NftEvent<'_> {
+    pub fn emit(&self) {
+        emit_event(NFT_EVENT_STANDARD, NFT_EVENT_VERSION, self);
+    }
+}
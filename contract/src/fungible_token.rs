@@ -0,0 +1,322 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{
+    env, ext_contract, near_bindgen, serde_json, AccountId, Balance, Gas, Promise, PromiseOrValue,
+};
+
+use crate::*;
+
+// トークンを送金する`ft_transfer`に使用するGAS
+const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+
+// NEP-141: トークンのコントラクトが実装する送金のメソッド
+// // トレイト自体は使用せず、`ext_contract`が生成する`ext_ft`からコールする
+#[allow(dead_code)]
+#[ext_contract(ext_ft)]
+trait FungibleToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+// `ft_transfer_call`の`msg`に指定する予約の内容
+// // 例: `{"room_id": "0", "check_in_date": "2222-01-01", "check_out_date": "2222-01-03"}`
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BookingMessage {
+    pub room_id: RoomId,
+    pub check_in_date: CheckInDate,
+    pub check_out_date: CheckOutDate,
+    // 宿泊する人数（指定しない場合は1人）
+    pub guests: Option<u8>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 宿泊料の支払いに使用できるトークンを追加する（管理者のみ）
+    pub fn add_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_admin();
+        self.accepted_tokens.insert(&token_id);
+    }
+
+    // 宿泊料の支払いに使用できるトークンから外す（管理者のみ）
+    // // 既存の予約の返金や、オーナーの出金には引き続き使用できる
+    pub fn remove_accepted_token(&mut self, token_id: AccountId) {
+        self.assert_admin();
+        self.accepted_tokens.remove(&token_id);
+    }
+
+    pub fn get_accepted_tokens(&self) -> Vec<AccountId> {
+        self.accepted_tokens.to_vec()
+    }
+
+    // 部屋の宿泊料を支払うトークンを設定する
+    // // `None`の場合はNEARで支払う。部屋の料金と料金ルールは、トークンの最小単位の金額として扱う
+    // // 預かっている宿泊料の種類が変わらないよう、予約が入っている部屋では変更できない
+    pub fn set_payment_token(&mut self, room_id: RoomId, payment_token: Option<AccountId>) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+        assert!(room.booked_info.is_empty(), "ERR_ROOM_HAS_BOOKINGS");
+        if let Some(ref token_id) = payment_token {
+            assert!(
+                self.accepted_tokens.contains(token_id),
+                "ERR_TOKEN_NOT_ACCEPTED"
+            );
+        }
+
+        room.payment_token = payment_token;
+        let initial_storage = env::storage_usage();
+        self.rooms_by_id.insert(&room_id, &room);
+        self.update_storage_usage(&room.owner_id, initial_storage);
+    }
+
+    // NEP-141: トークンで宿泊料を支払い、部屋を予約する
+    // // 宿泊者が`ft_transfer_call`をコールすると、トークンのコントラクトからコールされる
+    // // 使用しなかったトークンの数を返すと、トークンのコントラクトが宿泊者に返金する
+    // // パニックした場合は予約されず、送付されたトークンは全て返金される
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_id = env::predecessor_account_id();
+        assert!(
+            self.accepted_tokens.contains(&token_id),
+            "ERR_TOKEN_NOT_ACCEPTED"
+        );
+        let message: BookingMessage = serde_json::from_str(&msg).expect("ERR_INVALID_MESSAGE");
+
+        let (_, overpayment) = self.internal_book_room(
+            message.room_id,
+            sender_id,
+            message.check_in_date,
+            message.check_out_date,
+            message.guests,
+            amount.0,
+            Some(token_id),
+        );
+        PromiseOrValue::Value(U128(overpayment))
+    }
+}
+
+// `payment_token`で`amount`を送金する
+// // `None`の場合はNEARを送金する
+pub(crate) fn transfer(
+    receiver_id: AccountId,
+    payment_token: &Option<AccountId>,
+    amount: Balance,
+) -> Promise {
+    match payment_token {
+        None => Promise::new(receiver_id).transfer(amount),
+        Some(token_id) => ext_ft::ext(token_id.clone())
+            .with_attached_deposit(1)
+            .with_static_gas(GAS_FOR_FT_TRANSFER)
+            .ft_transfer(receiver_id, U128(amount), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::mock::VmAction;
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    fn token() -> AccountId {
+        "usdc.near".parse().unwrap()
+    }
+
+    fn message(room_id: &RoomId, check_in_date: &str, check_out_date: &str) -> String {
+        format!(
+            r#"{{"room_id":"{}","check_in_date":"{}","check_out_date":"{}"}}"#,
+            room_id, check_in_date, check_out_date
+        )
+    }
+
+    // 管理者の`alice`がトークンを追加し、オーナーの`bob`が1泊100トークンの部屋を登録する
+    fn setup(context: &mut VMContextBuilder) -> (Contract, RoomId) {
+        let mut contract = new_contract(context);
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.add_accepted_token(token());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        let room_id = add_room(&mut contract, "101", 100);
+        contract.set_payment_token(room_id.clone(), Some(token()));
+        (contract, room_id)
+    }
+
+    // トークンのコントラクトから`ft_on_transfer`がコールされる
+    fn pay_with_token(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        token_id: AccountId,
+        amount: u128,
+        msg: String,
+    ) -> U128 {
+        testing_env!(context
+            .predecessor_account_id(token_id)
+            .signer_account_id(accounts(2))
+            .build());
+        match contract.ft_on_transfer(accounts(2), U128(amount), msg) {
+            PromiseOrValue::Value(unused) => unused,
+            PromiseOrValue::Promise(_) => panic!("unexpected promise"),
+        }
+    }
+
+    // 作成されたトークンの送金（トークン、受取人と数量）を取得する
+    fn ft_transfers() -> Vec<(AccountId, AccountId, String)> {
+        let mut transfers = vec![];
+        for receipt in get_created_receipts() {
+            for action in receipt.actions {
+                if let VmAction::FunctionCall {
+                    function_name,
+                    args,
+                    deposit,
+                    ..
+                } = action
+                {
+                    if function_name != "ft_transfer" {
+                        continue;
+                    }
+                    assert_eq!(deposit, 1);
+                    let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+                    transfers.push((
+                        receipt.receiver_id.clone(),
+                        args["receiver_id"].as_str().unwrap().parse().unwrap(),
+                        args["amount"].as_str().unwrap().to_string(),
+                    ));
+                }
+            }
+        }
+        transfers
+    }
+
+    #[test]
+    fn book_room_with_token() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+
+        // 2泊分の200トークンを超えた分は返される
+        let unused = pay_with_token(
+            &mut context,
+            &mut contract,
+            token(),
+            250,
+            message(&room_id, "1970-01-02", "1970-01-04"),
+        );
+        assert_eq!(unused, U128(50));
+        let quote = contract.get_quote(
+            room_id.clone(),
+            date("1970-01-02"),
+            date("1970-01-04"),
+            None,
+        );
+        assert_eq!(quote.payment_token, Some(token()));
+        assert_eq!(quote.total, U128(200));
+        let guest_booked = contract.get_booking_info_for_guest(accounts(2), None, None);
+        assert_eq!(guest_booked[0].amount_paid, U128(200));
+        assert_eq!(
+            contract.get_escrow_balance(accounts(1), Some(token())),
+            U128(200)
+        );
+        assert_eq!(contract.get_escrow_balance(accounts(1), None), U128(0));
+
+        // チェックインすると、オーナーはトークンで出金できる
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .block_timestamp(timestamp("1970-01-02"))
+            .build());
+        contract.change_status_to_stay(room_id, date("1970-01-02"), accounts(2));
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), Some(token())),
+            U128(200)
+        );
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(0)
+        );
+
+        testing_env!(context.build());
+        contract.withdraw(None, Some(token()));
+        assert_eq!(
+            ft_transfers(),
+            vec![(token(), accounts(1), "200".to_string())]
+        );
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), Some(token())),
+            U128(0)
+        );
+    }
+
+    #[test]
+    fn cancel_booking_refunds_token() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        pay_with_token(
+            &mut context,
+            &mut contract,
+            token(),
+            100,
+            message(&room_id, "1970-01-02", "1970-01-03"),
+        );
+
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            .build());
+        contract.cancel_booking_by_owner(room_id, date("1970-01-02"));
+        assert_eq!(
+            ft_transfers(),
+            vec![(token(), accounts(2), "100".to_string())]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOKEN_NOT_ACCEPTED")]
+    fn pay_with_unknown_token() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        pay_with_token(
+            &mut context,
+            &mut contract,
+            accounts(3),
+            100,
+            message(&room_id, "1970-01-02", "1970-01-03"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_WRONG_PAYMENT_TOKEN")]
+    fn book_token_room_with_near() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        testing_env!(context
+            .predecessor_account_id(accounts(2))
+            .signer_account_id(accounts(2))
+            .attached_deposit(100)
+            .build());
+        contract.book_room(room_id, date("1970-01-02"), date("1970-01-03"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ROOM_HAS_BOOKINGS")]
+    fn change_payment_token_of_booked_room() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        pay_with_token(
+            &mut context,
+            &mut contract,
+            token(),
+            100,
+            message(&room_id, "1970-01-02", "1970-01-03"),
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_payment_token(room_id, None);
+    }
+}
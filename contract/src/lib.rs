@@ -1,14 +1,92 @@
+// `near_bindgen`が生成するコードには個別に指定できないため、クレート全体で許可する
+#![allow(clippy::too_many_arguments)]
+
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LookupMap;
+use near_sdk::collections::{LookupMap, TreeMap, UnorderedMap, UnorderedSet};
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::{env, near_bindgen, AccountId, Promise};
+use near_sdk::{
+    env, is_promise_success, near_bindgen, AccountId, Balance, Gas, PanicOnDefault, Promise,
+};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::vec;
 
+use crate::booking::BookingId;
+pub use crate::booking::{Booking, BookingStatus};
+pub use crate::date::Date;
+use crate::events::{
+    BookingCreatedData, BookingData, CancelledData, HotelEvent, RefundedData, RoomCreatedData,
+    RoomRemovedData, RoomUpdatedData,
+};
+use crate::fungible_token::transfer;
+pub use crate::migrate::QuarantinedBooking;
+use crate::migrate::{write_state_version, CURRENT_STATE_VERSION};
+pub use crate::non_fungible_token::{NFTContractMetadata, Token, TokenMetadata};
+use crate::pricing::{number_of_guests, price_of_stay};
+pub use crate::pricing::{DatePrice, NightlyPrice, PricingRules, Quote, SeasonalPrice};
+pub use crate::resale::ResalePolicy;
+use crate::storage::{refund, StorageAccount};
+pub use crate::storage::{StorageBalance, StorageBalanceBounds};
+
+mod booking;
+mod date;
+mod events;
+mod fungible_token;
+mod migrate;
+mod non_fungible_token;
+mod pricing;
+mod resale;
+mod storage;
+#[cfg(test)]
+mod test_utils;
+
 type RoomId = String;
-type CheckInDate = String;
+type CheckInDate = Date;
+type CheckOutDate = Date;
+
+// 部屋の予約を受け付ける期間（今日から何日先まで予約できるか）の既定値
+const DEFAULT_BOOKING_HORIZON_DAYS: u32 = 365;
+
+// 手数料率（ベーシスポイント）の上限（100%）
+const MAX_FEE_BPS: u16 = 10_000;
+
+// 一覧を取得する際に、`limit`を指定しなかった場合に返す件数
+const DEFAULT_PAGE_LIMIT: u64 = 50;
+
+// 出金結果を確認するコールバックに使用するGAS
+const GAS_FOR_ON_WITHDRAW: Gas = Gas(5_000_000_000_000);
+
+// 管理者が変更できる、コントラクト全体の設定
+#[derive(Serialize, Deserialize, Debug, BorshSerialize, BorshDeserialize, PartialEq, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Config {
+    // 宿泊料から差し引くプラットフォーム手数料（ベーシスポイント、10000で100%）
+    pub platform_fee_bps: u16,
+    // 1人のオーナーが登録できる部屋の数の上限
+    pub max_rooms_per_owner: u32,
+    // 部屋ごとに設定できる、予約を受け付ける期間の上限（日数）
+    pub max_booking_horizon_days: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            platform_fee_bps: 0,
+            max_rooms_per_owner: 100,
+            max_booking_horizon_days: 730,
+        }
+    }
+}
+
+// キャンセル時の返金ルール
+// // チェックインの`days_before`日前までにキャンセルされた場合、宿泊料の`refund_percent`%を返金する
+#[derive(Serialize, Deserialize, Debug, BorshSerialize, BorshDeserialize, PartialEq, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundRule {
+    days_before: u32,
+    refund_percent: u8,
+}
 
 #[derive(Serialize, Deserialize, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
@@ -17,16 +95,57 @@ pub enum UsageStatus {
     Stay { check_in_date: CheckInDate }, // 滞在中
 }
 
+// 予約できる部屋一覧を検索する条件
+// // 指定しなかった条件では絞り込まない
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoomQuery {
+    // 場所（大文字と小文字、空白の数は区別しない）
+    pub location: Option<String>,
+    // ベッドの数の下限
+    pub min_beds: Option<u8>,
+    // 宿泊期間の料金（各夜の料金の合計）の下限
+    pub min_price: Option<U128>,
+    // 宿泊期間の料金（各夜の料金の合計）の上限
+    pub max_price: Option<U128>,
+    // 部屋のオーナー
+    pub owner_id: Option<AccountId>,
+    // 並び順（指定しない場合は部屋のIDの昇順）
+    pub sort_by: Option<RoomSortBy>,
+}
+
+// 予約できる部屋一覧の並び順
+// // 確認する部屋の数をページごとに制限するため、ページをまたいで保てる部屋のIDの順のみ指定できる
+// // 料金やベッドの数で並べる場合は、全てのページを取得してからクライアントで並べ替える
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum RoomSortBy {
+    Newest, // 新しく登録された順
+}
+
+// 場所ごとの部屋の数（目的地の一覧を表示する際に使用）
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LocationSummary {
+    location: String,
+    room_count: u32,
+}
+
 // オーナーが登録した部屋一覧を表示する際に使用
 #[derive(Serialize, Deserialize, Debug, BorshSerialize, BorshDeserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ResigteredRoom {
+    room_id: RoomId,
     name: String,
     image: String,
     beds: u8,
     description: String,
     location: String,
     price: U128,
+    payment_token: Option<AccountId>,
+    booking_horizon_days: u32,
+    is_listed: bool,
     status: UsageStatus,
 }
 
@@ -34,10 +153,13 @@ pub struct ResigteredRoom {
 #[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug)]
 #[serde(crate = "near_sdk::serde")]
 pub struct BookedRoom {
+    booking_id: BookingId,
     room_id: RoomId,
     name: String,
     check_in_date: CheckInDate,
+    check_out_date: CheckOutDate,
     guest_id: AccountId,
+    amount_paid: U128,
     status: UsageStatus,
 }
 
@@ -53,15 +175,47 @@ pub struct AvailableRoom {
     description: String,
     location: String,
     price: U128,
+    payment_token: Option<AccountId>,
+}
+
+// 予約できる部屋の検索結果の1ページ
+// // `next_index`が`None`の場合は、全ての候補の部屋を確認済み
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AvailableRoomsPage {
+    pub rooms: Vec<AvailableRoom>,
+    pub next_index: Option<u64>,
 }
 
 // 宿泊者が予約を確認する際に使用
 #[derive(Serialize, Deserialize, Debug, BorshSerialize, BorshDeserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct GuestBookedRoom {
+    booking_id: BookingId,
+    room_id: RoomId,
     owner_id: AccountId,
     room_name: String,
     check_in_date: CheckInDate,
+    check_out_date: CheckOutDate,
+    amount_paid: U128,
+}
+
+// 部屋に入った予約（宿泊者、チェックアウト日と支払われた宿泊料）
+// // 部屋の料金が後から変更されても、予約時に支払われた宿泊料は変わらない
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct Stay {
+    // 予約の記録のID
+    booking_id: BookingId,
+    guest_id: AccountId,
+    check_out_date: CheckOutDate,
+    // チェックインまではコントラクトが預かり、キャンセル時の返金に使用する
+    amount_paid: U128,
+    // 予約した時点のプラットフォーム手数料率（ベーシスポイント）
+    // // 後から手数料率が変更されても、支払いが確定した時にはこの手数料率を適用する
+    platform_fee_bps: u16,
+    // 移行した予約など、保存に使用するストレージの料金をコントラクトが支払った予約
+    // // 削除しても、宿泊者のストレージの使用量からは差し引かない
+    storage_paid_by_contract: bool,
 }
 
 // 実際にスマートコントラクト内に保存される部屋のデータ
@@ -74,37 +228,138 @@ pub struct Room {
     description: String,
     location: String,
     price: U128,
+    // 宿泊料を支払うトークン（`None`の場合はNEAR）
+    payment_token: Option<AccountId>,
+    // 今日から何日先のチェックイン日まで予約を受け付けるか
+    booking_horizon_days: u32,
+    // `days_before`の降順に並んだ返金ルール
+    refund_policy: Vec<RefundRule>,
+    // 日付や曜日によって1泊の料金を変えるルール
+    pricing_rules: PricingRules,
+    // 予約の転売のルール（`None`の場合は転売を受け付けない）
+    resale_policy: Option<ResalePolicy>,
+    // `false`の時は予約できる部屋一覧に表示せず、新しい予約も受け付けない
+    is_listed: bool,
     status: UsageStatus,
-    booked_info: HashMap<CheckInDate, AccountId>,
+    // チェックイン日と予約の内容を紐付けて保持
+    booked_info: HashMap<CheckInDate, Stay>,
+    // 移行した部屋など、保存に使用するストレージの料金をコントラクトが支払った部屋
+    // // 削除しても、オーナーのストレージの使用量からは差し引かない
+    storage_paid_by_contract: bool,
 }
 
 // LookupMap: 反復処理を行わないデータに使用
+// UnorderedMap: 反復処理を行うデータに使用
+// // どちらもキーごとに個別に保存されるため、関数コールのたびに全てのデータを読み込むことはない
 // https://www.near-sdk.io/contract-structure/collections
 #[near_bindgen]
-#[derive(BorshSerialize, BorshDeserialize)]
+#[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
 pub struct Contract {
     // オーナーと所有する部屋のIDを紐付けて保持
     rooms_per_owner: LookupMap<AccountId, Vec<RoomId>>,
 
     // 部屋のIDと部屋のデータを紐付けて保持
-    rooms_by_id: HashMap<RoomId, Room>,
+    rooms_by_id: UnorderedMap<RoomId, Room>,
 
     // 宿泊者のアカウントIDと予約データを紐付けて保持
-    bookings_per_guest: HashMap<AccountId, HashMap<CheckInDate, RoomId>>,
-}
+    // // 同じチェックイン日に複数の部屋を予約できるよう、部屋のIDとチェックイン日の組で保持
+    bookings_per_guest: LookupMap<AccountId, HashSet<(RoomId, CheckInDate)>>,
 
-impl Default for Contract {
-    fn default() -> Self {
-        Self {
-            rooms_per_owner: LookupMap::new(b"m"),
-            rooms_by_id: HashMap::new(),
-            bookings_per_guest: HashMap::new(),
-        }
-    }
+    // オーナーと、部屋の利用状況の変更を任せたスタッフを紐付けて保持
+    staff_per_owner: LookupMap<AccountId, Vec<AccountId>>,
+
+    // オーナーと、チェックインによって支払いが確定し出金できる宿泊料を紐付けて保持
+    // // チェックイン前の宿泊料は各予約の`amount_paid`としてコントラクトが預かる
+    balance_per_owner: LookupMap<AccountId, u128>,
+
+    // 次に登録される部屋に割り当てるID
+    next_room_id: u64,
+
+    // 設定を変更できる管理者のアカウントID
+    admin_id: AccountId,
+
+    // コントラクト全体の設定
+    config: Config,
+
+    // アカウントと、ストレージの支払いに預けたNEARを紐付けて保持
+    storage_accounts: LookupMap<AccountId, StorageAccount>,
+
+    // 正規化した場所と、その場所にある部屋のIDを紐付けて保持
+    // // 場所の一覧をページごとに取得できるよう、場所の昇順に保持する
+    rooms_per_location: TreeMap<String, Vec<RoomId>>,
+
+    // 宿泊料から差し引き、管理者が出金できるプラットフォーム手数料の合計
+    accrued_fees: u128,
+
+    // 予約のIDと予約の記録を紐付けて保持
+    bookings_by_id: LookupMap<BookingId, Booking>,
+
+    // 次に予約に割り当てるID
+    next_booking_id: u64,
+
+    // 宿泊料の支払いに使用できるNEP-141トークンのコントラクト
+    accepted_tokens: UnorderedSet<AccountId>,
+
+    // オーナーとトークンの組と、出金できるトークンで支払われた宿泊料を紐付けて保持
+    token_balance_per_owner: LookupMap<(AccountId, AccountId), u128>,
+
+    // トークンと、管理者が出金できるトークンのプラットフォーム手数料の合計を紐付けて保持
+    accrued_token_fees: LookupMap<AccountId, u128>,
+
+    // 発行されている予約のNFTのID（チェックアウトやキャンセルの前の予約のID）
+    booking_tokens: UnorderedSet<BookingId>,
+
+    // 転売に出された予約のIDと転売価格を紐付けて保持
+    resale_prices: LookupMap<BookingId, u128>,
+
+    // 移行時に日付を変換できなかった以前の予約（部屋のIDと以前の日付の文字列の組と、宿泊者）
+    quarantined_bookings: UnorderedMap<(RoomId, String), AccountId>,
 }
 
 #[near_bindgen]
 impl Contract {
+    // コントラクトを初期化する
+    // // 初期化時に状態のバージョンを保存し、以降のアップグレードでは`migrate`で移行する
+    // // 初期化する前にコールされた関数は全て失敗する
+    #[init]
+    pub fn new(admin_id: AccountId, config: Config) -> Self {
+        assert_valid_config(&config);
+        write_state_version(CURRENT_STATE_VERSION);
+        Self::with_config(admin_id, config)
+    }
+
+    // 管理者のアカウントIDを取得する
+    pub fn get_admin(&self) -> AccountId {
+        self.admin_id.clone()
+    }
+
+    // コントラクト全体の設定を取得する
+    pub fn get_config(&self) -> Config {
+        self.config.clone()
+    }
+
+    // プラットフォーム手数料を変更する（管理者のみ）
+    // // 変更後の予約にのみ適用し、既存の予約には予約した時点の手数料率を適用する
+    pub fn set_platform_fee_bps(&mut self, platform_fee_bps: u16) {
+        self.assert_admin();
+        assert!(platform_fee_bps <= MAX_FEE_BPS, "ERR_INVALID_FEE");
+        self.config.platform_fee_bps = platform_fee_bps;
+    }
+
+    // 1人のオーナーが登録できる部屋の数の上限を変更する（管理者のみ）
+    // // 既に上限を超えて登録されている部屋はそのまま残る
+    pub fn set_max_rooms_per_owner(&mut self, max_rooms_per_owner: u32) {
+        self.assert_admin();
+        self.config.max_rooms_per_owner = max_rooms_per_owner;
+    }
+
+    // 予約を受け付ける期間の上限を変更する（管理者のみ）
+    // // 既に登録されている部屋にも、新しい上限が適用される
+    pub fn set_max_booking_horizon_days(&mut self, max_booking_horizon_days: u32) {
+        self.assert_admin();
+        self.config.max_booking_horizon_days = max_booking_horizon_days;
+    }
+
     pub fn add_room_to_owner(
         &mut self,
         name: String,
@@ -113,12 +368,40 @@ impl Contract {
         description: String,
         location: String,
         price: U128,
-    ) {
+        booking_horizon_days: Option<u32>,
+    ) -> RoomId {
         // 関数をコールしたアカウントIDを取得
-        let owner_id = env::signer_account_id();
+        let owner_id = env::predecessor_account_id();
+
+        // 同じオーナーが同じ名前の部屋を複数登録することはできない
+        assert!(
+            !self.exists(owner_id.clone(), name.clone()),
+            "ERR_ROOM_NAME_ALREADY_EXISTS"
+        );
+        let registered = self
+            .rooms_per_owner
+            .get(&owner_id)
+            .map_or(0, |rooms| rooms.len());
+        assert!(
+            registered < self.config.max_rooms_per_owner as usize,
+            "ERR_TOO_MANY_ROOMS"
+        );
+        let booking_horizon_days = booking_horizon_days.unwrap_or_else(|| {
+            DEFAULT_BOOKING_HORIZON_DAYS.min(self.config.max_booking_horizon_days)
+        });
+        assert!(
+            booking_horizon_days <= self.config.max_booking_horizon_days,
+            "ERR_BOOKING_HORIZON_TOO_LONG"
+        );
+
+        let initial_storage = env::storage_usage();
 
-        // 部屋のIDをオーナーのアカウントIDと部屋の名前で作成
-        let room_id = format!("{}{}", owner_id, name);
+        // 部屋のIDを連番で発行する
+        // // 部屋の名前やオーナーに依存しないため、IDが重複したり変わったりすることがない
+        let room_id = self.next_room_id.to_string();
+        self.next_room_id += 1;
+        let location = clean_location(&location);
+        self.add_room_to_location(&room_id, &location);
         let new_room = Room {
             owner_id: owner_id.clone(),
             name,
@@ -127,25 +410,96 @@ impl Contract {
             description,
             location,
             price,
+            payment_token: None,
+            booking_horizon_days,
+            refund_policy: vec![],
+            pricing_rules: PricingRules::default(),
+            resale_policy: None,
+            is_listed: true,
             status: UsageStatus::Available,
             booked_info: HashMap::new(),
+            storage_paid_by_contract: false,
         };
 
         // 部屋のデータを`room_id`と紐付けて保存
-        self.rooms_by_id.insert(room_id.clone(), new_room);
+        self.rooms_by_id.insert(&room_id, &new_room);
 
         match self.rooms_per_owner.get(&owner_id) {
             // オーナーが既に別の部屋を登録済みの時
             Some(mut rooms) => {
-                rooms.push(room_id);
+                rooms.push(room_id.clone());
                 self.rooms_per_owner.insert(&owner_id, &rooms);
             }
             // オーナーが初めて部屋を登録する時
             None => {
-                let new_rooms = vec![room_id];
+                let new_rooms = vec![room_id.clone()];
                 self.rooms_per_owner.insert(&owner_id, &new_rooms);
             }
         }
+        self.update_storage_usage(&owner_id, initial_storage);
+
+        HotelEvent::RoomCreated(vec![RoomCreatedData {
+            room_id: &room_id,
+            owner_id: &owner_id,
+            name: &new_room.name,
+            location: &new_room.location,
+            price: new_room.price,
+        }])
+        .emit();
+        room_id
+    }
+
+    // 部屋の情報を更新する
+    // // 指定された項目のみを更新する。料金の変更は既存の予約には影響しない
+    pub fn update_room(
+        &mut self,
+        room_id: RoomId,
+        image: Option<String>,
+        beds: Option<u8>,
+        description: Option<String>,
+        location: Option<String>,
+        price: Option<U128>,
+    ) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+
+        if let Some(ref image) = image {
+            room.image = image.clone();
+        }
+        if let Some(beds) = beds {
+            room.beds = beds;
+        }
+        if let Some(ref description) = description {
+            room.description = description.clone();
+        }
+        if let Some(price) = price {
+            room.price = price;
+        }
+        let initial_storage = env::storage_usage();
+        // 場所が変わった場合は、場所ごとの部屋のIDも付け替える
+        let location = location.map(|location| clean_location(&location));
+        if let Some(ref location) = location {
+            self.remove_room_from_location(&room_id, &room.location);
+            self.add_room_to_location(&room_id, location);
+            room.location = location.clone();
+        }
+        self.rooms_by_id.insert(&room_id, &room);
+        self.update_storage_usage(&room.owner_id, initial_storage);
+
+        HotelEvent::RoomUpdated(vec![RoomUpdatedData {
+            room_id: &room_id,
+            owner_id: &room.owner_id,
+            image: image.as_deref(),
+            beds,
+            description: description.as_deref(),
+            location: location.as_deref(),
+            price,
+        }])
+        .emit();
     }
 
     // 部屋の利用状況を`Stay -> Available`に変更する
@@ -155,320 +509,1300 @@ impl Contract {
         check_in_date: CheckInDate,
         guest_id: AccountId,
     ) {
-        let room = self
-            .rooms_by_id
-            .get_mut(&room_id)
-            .expect("ERR_NOT_FOUND_ROOM");
+        self.assert_owner_or_staff(&room_id);
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        // チェックイン済みの予約のみチェックアウトできる
+        assert_eq!(
+            room.status,
+            UsageStatus::Stay { check_in_date },
+            "ERR_NOT_CHECKED_IN"
+        );
 
-        // 部屋が持つ予約データの削除
-        room.booked_info
-            .remove(&check_in_date)
+        let stay = room
+            .booked_info
+            .get(&check_in_date)
             .expect("ERR_NOT_FOUND_DATE");
+        assert_eq!(stay.guest_id, guest_id, "ERR_NOT_GUEST");
 
+        // 部屋と宿泊者が持つ予約データを削除
+        let stay = self.remove_stay(&room_id, &mut room, check_in_date);
         room.status = UsageStatus::Available;
-
-        // 宿泊者が持つ予約データを削除
-        self.remove_booking_from_guest(guest_id, check_in_date);
+        self.rooms_by_id.insert(&room_id, &room);
+        self.set_booking_status(&stay.booking_id, BookingStatus::CheckedOut);
+
+        HotelEvent::CheckedOut(vec![BookingData {
+            booking_id: &stay.booking_id,
+            room_id: &room_id,
+            guest_id: &guest_id,
+            check_in_date,
+        }])
+        .emit();
     }
 
     // 部屋の利用状況を`Available -> Stay` に変更する
-    pub fn change_status_to_stay(&mut self, room_id: RoomId, check_in_date: CheckInDate) {
-        let room = self
-            .rooms_by_id
-            .get_mut(&room_id)
-            .expect("ERR_NOT_FOUND_ROOM");
-
+    // // `guest_id`は来訪した宿泊者で、予約のNFTを保有していなければチェックインできない
+    pub fn change_status_to_stay(
+        &mut self,
+        room_id: RoomId,
+        check_in_date: CheckInDate,
+        guest_id: AccountId,
+    ) {
+        self.assert_owner_or_staff(&room_id);
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            room.status,
+            UsageStatus::Available,
+            "ERR_ROOM_IS_NOT_AVAILABLE"
+        );
+        let stay = room
+            .booked_info
+            .get(&check_in_date)
+            .expect("ERR_NOT_FOUND_DATE");
+        assert_eq!(stay.guest_id, guest_id, "ERR_NOT_TOKEN_HOLDER");
+        // 宿泊料を預かっている意味がなくなるため、チェックイン日より前にはチェックインできない
+        assert!(today() >= check_in_date, "ERR_TOO_EARLY_TO_CHECK_IN");
+
+        // チェックインした時点で、預かっていた宿泊料をオーナーが出金できるようにする
+        let amount: u128 = stay.amount_paid.into();
+        let platform_fee_bps = stay.platform_fee_bps;
+        let booking_id = stay.booking_id.clone();
+        let owner_id = room.owner_id.clone();
         room.status = UsageStatus::Stay { check_in_date };
+        self.rooms_by_id.insert(&room_id, &room);
+        self.settle_to_owner(&owner_id, &room.payment_token, amount, platform_fee_bps);
+        self.set_booking_status(&booking_id, BookingStatus::CheckedIn);
+        // チェックインした予約は移転できないため、転売への出品を取り消す
+        let initial_storage = env::storage_usage();
+        self.resale_prices.remove(&booking_id);
+        self.update_storage_usage(&guest_id, initial_storage);
+
+        HotelEvent::CheckedIn(vec![BookingData {
+            booking_id: &booking_id,
+            room_id: &room_id,
+            guest_id: &guest_id,
+            check_in_date,
+        }])
+        .emit();
     }
 
-    // `room_id`が既に存在するかを確認する
-    // // 同じ部屋名を複数所有することは想定しないため、`add_room_to_owner`を実行する前にコールされる
-    pub fn exists(&self, owner_id: AccountId, room_name: String) -> bool {
-        let room_id = format!("{}{}", owner_id, room_name);
+    // 部屋の返金ルールを設定する
+    // // 例: `[{days_before: 7, refund_percent: 100}, {days_before: 1, refund_percent: 50}]`
+    // //     7日前までは全額、1日前までは半額を返金し、それ以降は返金しない
+    pub fn set_refund_policy(&mut self, room_id: RoomId, refund_policy: Vec<RefundRule>) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
 
-        self.rooms_by_id.contains_key(&room_id)
+        let mut refund_policy = refund_policy;
+        for rule in refund_policy.iter() {
+            assert!(rule.refund_percent <= 100, "ERR_INVALID_REFUND_PERCENT");
+        }
+        refund_policy.sort_by_key(|rule| std::cmp::Reverse(rule.days_before));
+        room.refund_policy = refund_policy;
+        let initial_storage = env::storage_usage();
+        self.rooms_by_id.insert(&room_id, &room);
+        self.update_storage_usage(&room.owner_id, initial_storage);
     }
 
-    // changeメソッドの`change_status_to_stay`を実行する前に、部屋の利用状況を確認する
-    pub fn is_available(&self, room_id: RoomId) -> bool {
+    // 部屋の返金ルールを取得する
+    pub fn get_refund_policy(&self, room_id: RoomId) -> Vec<RefundRule> {
         let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        room.refund_policy.clone()
+    }
 
-        if room.status != UsageStatus::Available {
-            return false;
+    // 宿泊者が予約をキャンセルする
+    // // 返金ルールに従って宿泊料の一部を宿泊者へ返金し、残りを部屋のオーナーへ支払う
+    pub fn cancel_booking(&mut self, room_id: RoomId, check_in_date: CheckInDate) -> U128 {
+        let guest_id = env::predecessor_account_id();
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+
+        let stay = room
+            .booked_info
+            .get(&check_in_date)
+            .expect("ERR_NOT_FOUND_DATE");
+        assert_eq!(stay.guest_id, guest_id, "ERR_NOT_GUEST");
+        // チェックイン済みの予約はキャンセルできない
+        assert_ne!(
+            room.status,
+            UsageStatus::Stay { check_in_date },
+            "ERR_ALREADY_CHECKED_IN"
+        );
+
+        let amount_paid: u128 = stay.amount_paid.into();
+        let days_before = today().days_until(check_in_date);
+        let refund = amount_paid * refund_percent(&room.refund_policy, days_before) as u128 / 100;
+        let owner_id = room.owner_id.clone();
+
+        // 部屋と宿泊者が持つ予約データを削除
+        let stay = self.remove_stay(&room_id, &mut room, check_in_date);
+        self.set_booking_status(&stay.booking_id, BookingStatus::Cancelled);
+
+        HotelEvent::Cancelled(vec![CancelledData {
+            booking_id: &stay.booking_id,
+            room_id: &room_id,
+            guest_id: &guest_id,
+            check_in_date,
+            cancelled_by: &guest_id,
+        }])
+        .emit();
+        if refund > 0 {
+            HotelEvent::Refunded(vec![RefundedData {
+                booking_id: &stay.booking_id,
+                room_id: &room_id,
+                guest_id: &guest_id,
+                check_in_date,
+                amount: U128(refund),
+            }])
+            .emit();
+            self.refund_to_guest(guest_id, &room.payment_token, refund);
         }
-        true
+        self.settle_to_owner(
+            &owner_id,
+            &room.payment_token,
+            amount_paid - refund,
+            stay.platform_fee_bps,
+        );
+        U128(refund)
     }
 
-    // 宿泊希望日に予約できる部屋一覧を取得する
-    pub fn get_available_rooms(&self, check_in_date: CheckInDate) -> Vec<AvailableRoom> {
-        let mut available_rooms = vec![];
+    // オーナーが予約をキャンセルし、宿泊料を全額返金する
+    pub fn cancel_booking_by_owner(&mut self, room_id: RoomId, check_in_date: CheckInDate) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+        // チェックイン済みの予約はキャンセルできない
+        assert_ne!(
+            room.status,
+            UsageStatus::Stay { check_in_date },
+            "ERR_ALREADY_CHECKED_IN"
+        );
 
-        for (room_id, room) in self.rooms_by_id.iter() {
-            match room.booked_info.get(&check_in_date) {
-                // 宿泊希望日に既に予約が入っていたら何もしない
-                Some(_) => {
-                    continue;
-                }
-                // 予約が入っていなかったら、部屋のデータを作成
-                None => {
-                    let available_room = AvailableRoom {
-                        room_id: room_id.clone(),
-                        owner_id: room.owner_id.clone(),
-                        name: room.name.clone(),
-                        beds: room.beds,
-                        image: room.image.clone(),
-                        description: room.description.clone(),
-                        location: room.location.clone(),
-                        price: room.price,
-                    };
-                    available_rooms.push(available_room);
-                }
-            }
+        // 部屋と宿泊者が持つ予約データを削除
+        let stay = self.remove_stay(&room_id, &mut room, check_in_date);
+        self.set_booking_status(&stay.booking_id, BookingStatus::Cancelled);
+
+        HotelEvent::Cancelled(vec![CancelledData {
+            booking_id: &stay.booking_id,
+            room_id: &room_id,
+            guest_id: &stay.guest_id,
+            check_in_date,
+            cancelled_by: &room.owner_id,
+        }])
+        .emit();
+        let amount_paid: u128 = stay.amount_paid.into();
+        if amount_paid > 0 {
+            HotelEvent::Refunded(vec![RefundedData {
+                booking_id: &stay.booking_id,
+                room_id: &room_id,
+                guest_id: &stay.guest_id,
+                check_in_date,
+                amount: stay.amount_paid,
+            }])
+            .emit();
+            self.refund_to_guest(stay.guest_id, &room.payment_token, amount_paid);
         }
-        available_rooms
     }
 
-    // オーナーが登録した部屋の一覧を取得する
-    pub fn get_rooms_registered_by_owner(&self, owner_id: AccountId) -> Vec<ResigteredRoom> {
-        let mut registered_rooms = vec![];
+    // 部屋を予約できる部屋一覧から外す
+    // // 部屋のデータと既存の予約はそのまま残る
+    pub fn delist_room(&mut self, room_id: RoomId) {
+        self.set_listed(room_id, false);
+    }
 
-        match self.rooms_per_owner.get(&owner_id) {
-            Some(rooms) => {
-                for room_id in rooms {
-                    // 登録された部屋ごとにデータを作成
-                    let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
-
-                    // UseStatusを複製
-                    // `String`はCopyトレイトを持つことができないため、自分でコピーを作成する必要がある
-                    let status: UsageStatus = match room.status {
-                        UsageStatus::Available => UsageStatus::Available,
-                        UsageStatus::Stay { ref check_in_date } => UsageStatus::Stay {
-                            check_in_date: check_in_date.clone(),
-                        },
-                    };
-
-                    let resigtered_room = ResigteredRoom {
-                        name: room.name.clone(),
-                        beds: room.beds,
-                        image: room.image.clone(),
-                        description: room.description.clone(),
-                        location: room.location.clone(),
-                        price: room.price,
-                        status,
-                    };
-                    registered_rooms.push(resigtered_room);
-                }
-                registered_rooms
-            }
-            None => registered_rooms,
+    // 予約できる部屋一覧に部屋を戻す
+    pub fn relist_room(&mut self, room_id: RoomId) {
+        self.set_listed(room_id, true);
+    }
+
+    // 部屋を削除する
+    // // チェックアウト日が今日より後の予約や滞在中の宿泊者がいる場合は削除できないため、先に予約をキャンセルする必要がある
+    // // 宿泊者は遅れてチェックインできるため、チェックイン日が過ぎていても宿泊期間中の予約は削除できない
+    // // 移行時に隔離された予約がある場合も、先に`resolve_quarantined_booking`で予約に戻すか削除する必要がある
+    pub fn remove_room(&mut self, room_id: RoomId) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        let owner_id = room.owner_id.clone();
+        assert_eq!(
+            env::predecessor_account_id(),
+            owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+        assert_eq!(room.status, UsageStatus::Available, "ERR_ROOM_IS_OCCUPIED");
+        let today = today();
+        assert!(
+            room.booked_info
+                .values()
+                .all(|stay| stay.check_out_date <= today),
+            "ERR_ROOM_HAS_FUTURE_BOOKINGS"
+        );
+        assert!(
+            self.quarantined_bookings
+                .keys()
+                .all(|(quarantined_room_id, _)| quarantined_room_id != room_id),
+            "ERR_ROOM_HAS_QUARANTINED_BOOKINGS"
+        );
+
+        // チェックインされないまま宿泊期間が終わった予約は、宿泊料をオーナーへ支払って削除する
+        let check_in_dates: Vec<CheckInDate> = room.booked_info.keys().copied().collect();
+        for check_in_date in check_in_dates {
+            let stay = self.remove_stay(&room_id, &mut room, check_in_date);
+            self.settle_to_owner(
+                &owner_id,
+                &room.payment_token,
+                stay.amount_paid.into(),
+                stay.platform_fee_bps,
+            );
+            self.set_booking_status(&stay.booking_id, BookingStatus::NoShow);
+        }
+
+        let initial_storage = env::storage_usage();
+        self.rooms_by_id.remove(&room_id);
+        self.remove_room_from_location(&room_id, &room.location);
+        let mut rooms = self
+            .rooms_per_owner
+            .get(&owner_id)
+            .expect("ERR_NOT_FOUND_ROOM");
+        rooms.retain(|id| id != &room_id);
+        if rooms.is_empty() {
+            self.rooms_per_owner.remove(&owner_id);
+        } else {
+            self.rooms_per_owner.insert(&owner_id, &rooms);
         }
+        if !room.storage_paid_by_contract {
+            self.update_storage_usage(&owner_id, initial_storage);
+        }
+
+        HotelEvent::RoomRemoved(vec![RoomRemovedData {
+            room_id: &room_id,
+            owner_id: &owner_id,
+        }])
+        .emit();
     }
 
-    // 予約一覧を取得する
-    pub fn get_booking_info_for_owner(&self, owner_id: AccountId) -> Vec<BookedRoom> {
-        let mut booked_rooms = vec![];
+    // チェックイン前の予約の宿泊料として、コントラクトが預かっている金額を取得する
+    // // `token_id`で支払われる部屋の宿泊料のみを合計する（`None`の場合はNEAR）
+    pub fn get_escrow_balance(&self, owner_id: AccountId, token_id: Option<AccountId>) -> U128 {
+        let mut escrow: u128 = 0;
 
-        match self.rooms_per_owner.get(&owner_id) {
-            Some(rooms) => {
-                for room_id in rooms.iter() {
-                    let room = self.rooms_by_id.get(room_id).expect("ERR_NOT_FOUND_ROOM");
-                    // 予約がなければ何もしない
-                    if room.booked_info.is_empty() {
+        if let Some(rooms) = self.rooms_per_owner.get(&owner_id) {
+            for room_id in rooms.iter() {
+                let room = self.rooms_by_id.get(room_id).expect("ERR_NOT_FOUND_ROOM");
+                if room.payment_token != token_id {
+                    continue;
+                }
+                for (check_in_date, stay) in room.booked_info.iter() {
+                    // チェックイン済みの予約の宿泊料は既にオーナーへ支払われている
+                    if room.status
+                        == (UsageStatus::Stay {
+                            check_in_date: *check_in_date,
+                        })
+                    {
                         continue;
                     }
-                    // 予約された日付ごとに予約データを作成
-                    for (date, guest_id) in room.booked_info.clone() {
-                        // UseStatusを複製
-                        let status: UsageStatus;
-                        match room.status {
-                            UsageStatus::Available => {
-                                status = UsageStatus::Available;
-                            }
-                            UsageStatus::Stay { ref check_in_date } => {
-                                if date == check_in_date.clone() {
-                                    status = UsageStatus::Stay {
-                                        check_in_date: check_in_date.clone(),
-                                    };
-                                } else {
-                                    status = UsageStatus::Available;
-                                }
-                            }
-                        }
-                        let booked_room = BookedRoom {
-                            room_id: room_id.to_string(),
-                            name: room.name.clone(),
-                            check_in_date: date,
-                            guest_id,
-                            status,
-                        };
-                        booked_rooms.push(booked_room);
-                    }
+                    escrow += u128::from(stay.amount_paid);
                 }
-                booked_rooms
             }
-            // 部屋を登録していない場合は空が返る
-            None => booked_rooms,
         }
+        U128(escrow)
     }
 
-    // 宿泊者に表示する予約データを取得
-    pub fn get_booking_info_for_guest(&self, guest_id: AccountId) -> Vec<GuestBookedRoom> {
-        let mut guest_info: Vec<GuestBookedRoom> = vec![];
-        match self.bookings_per_guest.get(&guest_id) {
-            Some(save_booked_info) => {
-                for (check_in_date, room_id) in save_booked_info {
-                    let room = self.rooms_by_id.get(room_id).expect("ERR_NOT_FOUND_ROOM");
-                    let info = GuestBookedRoom {
-                        owner_id: room.owner_id.clone(),
-                        room_name: room.name.clone(),
-                        check_in_date: check_in_date.clone(),
-                    };
-                    guest_info.push(info);
-                }
-                guest_info
-            }
-            None => guest_info,
-        }
+    // オーナーが`token_id`で出金できる金額を取得する（`None`の場合はNEAR）
+    // // 返金の送金に失敗した宿泊者や、予約を転売した出品者の出金できる金額も取得できる
+    pub fn get_withdrawable_balance(
+        &self,
+        owner_id: AccountId,
+        token_id: Option<AccountId>,
+    ) -> U128 {
+        U128(self.balance_of(&owner_id, &token_id))
     }
 
-    // 部屋を予約する
-    #[payable]
-    pub fn book_room(&mut self, room_id: RoomId, check_in_date: CheckInDate) {
-        let room = self
-            .rooms_by_id
-            .get_mut(&room_id)
-            .expect("ERR_NOT_FOUND_ROOM");
-
-        let account_id = env::signer_account_id();
-
-        // 関数コール時に送付されたNEARを取得
-        let deposit = env::attached_deposit();
-        // 送付されたNEARと実際の宿泊料（NEAR）を比較するためにキャストをする
-        let room_price: u128 = room.price.into();
-        assert_eq!(deposit, room_price, "ERR_DEPOSIT_IS_INCORRECT");
+    // 支払いが確定した宿泊料をオーナーへ送金する
+    // // 返金の送金に失敗した宿泊者や予約を転売した出品者も、同じ方法で出金する
+    // // `amount`を指定しない場合は全額を出金する。`token_id`を指定した場合はそのトークンで支払われた分を出金する
+    pub fn withdraw(&mut self, amount: Option<U128>, token_id: Option<AccountId>) -> Promise {
+        let owner_id = env::predecessor_account_id();
+        let balance = self.balance_of(&owner_id, &token_id);
+        let amount = amount.map_or(balance, u128::from);
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+        assert!(amount <= balance, "ERR_NOT_ENOUGH_BALANCE");
+
+        self.set_balance(&owner_id, &token_id, balance - amount);
+        transfer(owner_id.clone(), &token_id, amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_ON_WITHDRAW)
+                .on_withdraw(owner_id, U128(amount), token_id),
+        )
+    }
 
-        // 予約が入った日付, 宿泊者IDを登録
-        room.booked_info
-            .insert(check_in_date.clone(), account_id.clone());
+    // 送金に失敗した場合、出金した金額をオーナーの残高に戻す
+    #[private]
+    pub fn on_withdraw(&mut self, owner_id: AccountId, amount: U128, token_id: Option<AccountId>) {
+        if !is_promise_success() {
+            self.release_to_owner(&owner_id, &token_id, amount.into());
+        }
+    }
 
-        // 宿泊者に予約データを保存
-        let owner_id = room.owner_id.clone();
-        self.add_booking_to_guest(account_id, room_id, check_in_date);
+    // 返金の送金に失敗した場合、返金する金額を宿泊者の出金できる残高に加算する
+    // // 宿泊者は`withdraw`で出金する
+    #[private]
+    pub fn on_refund(&mut self, guest_id: AccountId, amount: U128, token_id: Option<AccountId>) {
+        if !is_promise_success() {
+            self.release_to_owner(&guest_id, &token_id, amount.into());
+        }
+    }
 
-        // 宿泊料を部屋のオーナーへ支払う
-        Promise::new(owner_id).transfer(deposit);
+    // 管理者が`token_id`で出金できるプラットフォーム手数料の合計を取得する（`None`の場合はNEAR）
+    pub fn get_accrued_fees(&self, token_id: Option<AccountId>) -> U128 {
+        U128(self.accrued_fees_of(&token_id))
     }
-}
 
-// Private functions
-impl Contract {
-    // 予約データを宿泊者用に保存する
-    fn add_booking_to_guest(
+    // プラットフォーム手数料を`receiver_id`へ送金する
+    // // `amount`を指定しない場合は全額を出金する
+    pub fn withdraw_fees(
         &mut self,
-        guest_id: AccountId,
-        room_id: RoomId,
-        check_in_date: CheckInDate,
-    ) {
-        match self.bookings_per_guest.get_mut(&guest_id) {
-            // 宿泊者が既に別の予約データを所有している時
-            Some(booked_date) => {
-                booked_date.insert(check_in_date, room_id);
-            }
-            // 初めて予約データを保存する時
-            None => {
-                let mut new_guest_date = HashMap::new();
-                new_guest_date.insert(check_in_date, room_id);
-                self.bookings_per_guest.insert(guest_id, new_guest_date);
-            }
+        amount: Option<U128>,
+        receiver_id: AccountId,
+        token_id: Option<AccountId>,
+    ) -> Promise {
+        self.assert_admin();
+        let accrued_fees = self.accrued_fees_of(&token_id);
+        let amount = amount.map_or(accrued_fees, u128::from);
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+        assert!(amount <= accrued_fees, "ERR_NOT_ENOUGH_BALANCE");
+
+        self.set_accrued_fees(&token_id, accrued_fees - amount);
+        transfer(receiver_id, &token_id, amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_ON_WITHDRAW)
+                .on_withdraw_fees(U128(amount), token_id),
+        )
+    }
+
+    // 送金に失敗した場合、出金した金額をプラットフォーム手数料に戻す
+    #[private]
+    pub fn on_withdraw_fees(&mut self, amount: U128, token_id: Option<AccountId>) {
+        if !is_promise_success() {
+            let accrued_fees = self.accrued_fees_of(&token_id);
+            self.set_accrued_fees(&token_id, accrued_fees + amount.0);
         }
     }
 
-    // 宿泊者の持つ予約データから、宿泊済みのデータを削除する
-    fn remove_booking_from_guest(&mut self, guest_id: AccountId, check_in_data: CheckInDate) {
-        // 宿泊者が持っている予約データのmapを取得
-        let book_info = self
-            .bookings_per_guest
-            .get_mut(&guest_id)
-            .expect("ERR_NOT_FOUND_GUEST");
+    // 部屋の利用状況の変更をスタッフに任せる
+    pub fn add_staff(&mut self, staff_id: AccountId) {
+        let owner_id = env::predecessor_account_id();
 
-        book_info
-            .remove(&check_in_data)
-            .expect("ERR_NOT_FOUND_BOOKED");
+        let mut staff = self.staff_per_owner.get(&owner_id).unwrap_or_default();
+        if !staff.contains(&staff_id) {
+            let initial_storage = env::storage_usage();
+            staff.push(staff_id);
+            self.staff_per_owner.insert(&owner_id, &staff);
+            self.update_storage_usage(&owner_id, initial_storage);
+        }
+    }
 
-        // 予約データが空になった場合、`bookings_per_guest`からゲストを削除する
-        if book_info.is_empty() {
-            self.bookings_per_guest.remove(&guest_id);
+    // スタッフに任せた権限を取り消す
+    pub fn remove_staff(&mut self, staff_id: AccountId) {
+        let owner_id = env::predecessor_account_id();
+
+        let mut staff = self
+            .staff_per_owner
+            .get(&owner_id)
+            .expect("ERR_NOT_FOUND_STAFF");
+        let len = staff.len();
+        staff.retain(|id| id != &staff_id);
+        assert_ne!(staff.len(), len, "ERR_NOT_FOUND_STAFF");
+
+        let initial_storage = env::storage_usage();
+        if staff.is_empty() {
+            self.staff_per_owner.remove(&owner_id);
+        } else {
+            self.staff_per_owner.insert(&owner_id, &staff);
         }
+        self.update_storage_usage(&owner_id, initial_storage);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
-    use near_sdk::testing_env;
-    // トランザクションを実行するテスト環境を設定
-    fn get_context(is_view: bool) -> VMContextBuilder {
-        let mut builder = VMContextBuilder::new();
-        builder
-            .current_account_id(accounts(0))
-            .predecessor_account_id(accounts(0))
-            .signer_account_id(accounts(1))
-            // 使用するメソッドをbooleanで指定(viewメソッドはtrue, changeメソッドはfalse)
-            .is_view(is_view);
-        builder
+    // オーナーが登録したスタッフの一覧を取得する
+    // // 登録した順に、`from_index`件目から最大`limit`件を返す
+    pub fn get_staff(
+        &self,
+        owner_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let staff = self.staff_per_owner.get(&owner_id).unwrap_or_default();
+        paginate(staff.into_iter(), from_index, limit)
     }
-    #[test]
-    fn add_then_get_registered_rooms() {
-        let context = get_context(false);
-        testing_env!(context.build());
-        let mut contract = Contract::default();
-        contract.add_room_to_owner(
-            "101".to_string(),
-            "test.img".to_string(),
-            1,
-            "This is 101 room".to_string(),
-            "Tokyo".to_string(),
-            U128(10),
-        );
-        contract.add_room_to_owner(
-            "201".to_string(),
-            "test.img".to_string(),
-            1,
-            "This is 201 room".to_string(),
-            "Tokyo".to_string(),
-            U128(10),
-        );
-        // add_room_to_owner関数をコールしたアカウントIDを取得
-        let owner_id = env::signer_account_id();
 
-        let all_rooms = contract.get_rooms_registered_by_owner(owner_id);
-        assert_eq!(all_rooms.len(), 2);
+    // オーナーが同じ名前の部屋を既に登録しているかを確認する
+    // // 同じ部屋名を複数所有することはできないため、`add_room_to_owner`を実行する前にコールされる
+    pub fn exists(&self, owner_id: AccountId, room_name: String) -> bool {
+        match self.rooms_per_owner.get(&owner_id) {
+            Some(rooms) => rooms.iter().any(|room_id| {
+                let room = self.rooms_by_id.get(room_id).expect("ERR_NOT_FOUND_ROOM");
+                room.name == room_name
+            }),
+            None => false,
+        }
     }
 
-    #[test]
-    fn no_registered_room() {
-        let context = get_context(true);
-        testing_env!(context.build());
-        let contract = Contract::default();
+    // changeメソッドの`change_status_to_stay`を実行する前に、部屋の利用状況を確認する
+    pub fn is_available(&self, room_id: RoomId) -> bool {
+        let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
 
-        let no_registered_room = contract.get_rooms_registered_by_owner(accounts(0));
-        assert_eq!(no_registered_room.len(), 0);
+        if room.status != UsageStatus::Available {
+            return false;
+        }
+        true
     }
 
-    #[test]
-    fn add_then_get_available_rooms() {
-        let mut context = get_context(false);
-        testing_env!(context.build());
+    // 宿泊希望期間の全ての夜に予約できる部屋一覧を、`query`の条件で検索する
+    // // 候補の部屋を`query.sort_by`の順に`from_index`件目から最大`limit`件だけ確認し、その中で条件に合う部屋を返す
+    // // 部屋の数が増えてもガスの上限を超えないよう、確認する部屋の数を制限する。続きは`next_index`から検索する
+    pub fn get_available_rooms(
+        &self,
+        check_in_date: CheckInDate,
+        check_out_date: CheckOutDate,
+        query: Option<RoomQuery>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> AvailableRoomsPage {
+        nights_of_stay(check_in_date, check_out_date);
+        let today = today();
+        let query = query.unwrap_or_default();
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        let newest = query.sort_by == Some(RoomSortBy::Newest);
+
+        // オーナーや場所を指定した場合は、そのオーナーや場所の部屋のみを候補とする
+        // // 候補の部屋は、部屋のIDの昇順（`Newest`の場合は降順）に並べる
+        let room_ids_of_query = match (&query.owner_id, &query.location) {
+            (Some(owner_id), _) => Some(self.rooms_per_owner.get(owner_id).unwrap_or_default()),
+            (None, Some(location)) => Some(self.room_ids_at(location)),
+            (None, None) => None,
+        };
+        let (candidate_count, room_ids) = match room_ids_of_query {
+            Some(mut room_ids) => {
+                if newest {
+                    room_ids.reverse();
+                }
+                let count = room_ids.len() as u64;
+                (
+                    count,
+                    paginate(room_ids.into_iter(), Some(from_index), Some(limit)),
+                )
+            }
+            // 全ての部屋が候補の場合は、確認する範囲の部屋のIDのみを作る
+            None => {
+                let count = self.next_room_id;
+                let end = from_index.saturating_add(limit).min(count);
+                let room_ids = (from_index.min(end)..end)
+                    .map(|index| if newest { count - 1 - index } else { index })
+                    .map(|id| id.to_string())
+                    .collect();
+                (count, room_ids)
+            }
+        };
 
-        let mut contract = Contract::default();
-        contract.add_room_to_owner(
-            "101".to_string(),
+        let rooms = room_ids
+            .into_iter()
+            // 削除された部屋のIDは飛ばす
+            .filter_map(|room_id| self.rooms_by_id.get(&room_id).map(|room| (room_id, room)))
+            // 予約を受け付けていない日付、または既に予約が入っている部屋は除く
+            .filter(|(_, room)| {
+                room.is_listed
+                    && is_within_horizon(room, &self.config, check_in_date, today)
+                    && is_within_horizon(room, &self.config, check_out_date.add_days(-1), today)
+                    && is_free(room, check_in_date, check_out_date)
+            })
+            // 料金の条件には、季節料金などを反映した宿泊期間の料金を使用する
+            .filter(|(_, room)| {
+                let price = price_of_stay(room, check_in_date, check_out_date);
+                matches_query(room, price, &query)
+            })
+            .map(|(room_id, room)| available_room(room_id, room));
+
+        let next_index = from_index.saturating_add(limit);
+        AvailableRoomsPage {
+            rooms: rooms.collect(),
+            next_index: (next_index < candidate_count).then_some(next_index),
+        }
+    }
+
+    // 部屋がある場所と、場所ごとの部屋の数を取得する
+    // // 場所は正規化（小文字）した値で、場所の昇順に`from_index`件目から最大`limit`件を返す
+    pub fn get_locations(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<LocationSummary> {
+        // 場所のキーのみを昇順に辿り、取得するページの場所の部屋のIDだけを読み込む
+        let locations = std::iter::successors(self.rooms_per_location.min(), |location| {
+            self.rooms_per_location.higher(location)
+        });
+        paginate(locations, from_index, limit)
+            .into_iter()
+            .map(|location| {
+                let room_ids = self
+                    .rooms_per_location
+                    .get(&location)
+                    .expect("ERR_NOT_FOUND_LOCATION");
+                LocationSummary {
+                    location,
+                    room_count: room_ids.len() as u32,
+                }
+            })
+            .collect()
+    }
+
+    // 場所にある、予約を受け付けている部屋の一覧を取得する
+    // // 部屋のIDの昇順に、`from_index`件目から最大`limit`件を返す
+    pub fn get_rooms_by_location(
+        &self,
+        location: String,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AvailableRoom> {
+        let rooms = self
+            .room_ids_at(&location)
+            .into_iter()
+            .map(|room_id| {
+                let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+                (room_id, room)
+            })
+            .filter(|(_, room)| room.is_listed)
+            .map(|(room_id, room)| available_room(room_id, room));
+        paginate(rooms, from_index, limit)
+    }
+
+    // オーナーが登録した部屋の一覧を取得する
+    // // 登録した順に、`from_index`件目から最大`limit`件を返す
+    pub fn get_rooms_registered_by_owner(
+        &self,
+        owner_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<ResigteredRoom> {
+        let room_ids = self.rooms_per_owner.get(&owner_id).unwrap_or_default();
+
+        let registered_rooms = room_ids.into_iter().map(|room_id| {
+            // 登録された部屋ごとにデータを作成
+            let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+            ResigteredRoom {
+                room_id,
+                name: room.name,
+                beds: room.beds,
+                image: room.image,
+                description: room.description,
+                location: room.location,
+                price: room.price,
+                payment_token: room.payment_token,
+                booking_horizon_days: room.booking_horizon_days,
+                is_listed: room.is_listed,
+                status: room.status,
+            }
+        });
+        paginate(registered_rooms, from_index, limit)
+    }
+
+    // 予約一覧を取得する
+    // // 部屋を登録した順、同じ部屋の中ではチェックイン日の昇順に、`from_index`件目から最大`limit`件を返す
+    pub fn get_booking_info_for_owner(
+        &self,
+        owner_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<BookedRoom> {
+        let room_ids = self.rooms_per_owner.get(&owner_id).unwrap_or_default();
+
+        let booked_rooms = room_ids.into_iter().flat_map(|room_id| {
+            let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+            let mut stays: Vec<(CheckInDate, Stay)> = room.booked_info.into_iter().collect();
+            stays.sort_by_key(|(check_in_date, _)| *check_in_date);
+
+            // 予約された日付ごとに予約データを作成
+            // // 滞在中の予約のみ`Stay`、それ以外は`Available`とする
+            let status = room.status;
+            let name = room.name;
+            stays
+                .into_iter()
+                .map(move |(check_in_date, stay)| BookedRoom {
+                    booking_id: stay.booking_id,
+                    room_id: room_id.clone(),
+                    name: name.clone(),
+                    check_in_date,
+                    check_out_date: stay.check_out_date,
+                    guest_id: stay.guest_id,
+                    amount_paid: stay.amount_paid,
+                    status: if status == (UsageStatus::Stay { check_in_date }) {
+                        UsageStatus::Stay { check_in_date }
+                    } else {
+                        UsageStatus::Available
+                    },
+                })
+        });
+        paginate(booked_rooms, from_index, limit)
+    }
+
+    // 宿泊者に表示する予約データを取得
+    // // チェックイン日の昇順に、`from_index`件目から最大`limit`件を返す
+    pub fn get_booking_info_for_guest(
+        &self,
+        guest_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<GuestBookedRoom> {
+        let mut bookings: Vec<(RoomId, CheckInDate)> = self
+            .bookings_per_guest
+            .get(&guest_id)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        bookings.sort_by_key(|(room_id, check_in_date)| (*check_in_date, room_id_order(room_id)));
+
+        let guest_info = bookings.into_iter().map(|(room_id, check_in_date)| {
+            let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+            let stay = room
+                .booked_info
+                .get(&check_in_date)
+                .expect("ERR_NOT_FOUND_DATE");
+            GuestBookedRoom {
+                booking_id: stay.booking_id.clone(),
+                room_id,
+                owner_id: room.owner_id.clone(),
+                room_name: room.name.clone(),
+                check_in_date,
+                check_out_date: stay.check_out_date,
+                amount_paid: stay.amount_paid,
+            }
+        });
+        paginate(guest_info, from_index, limit)
+    }
+
+    // 部屋を予約する
+    // // チェックイン日からチェックアウト日の前日までの夜が宿泊期間となる
+    // // `guests`は`get_quote`と同じく、指定しない場合は1人として扱う
+    // // トークンで支払う部屋は、トークンの`ft_transfer_call`で予約する
+    #[payable]
+    pub fn book_room(
+        &mut self,
+        room_id: RoomId,
+        check_in_date: CheckInDate,
+        check_out_date: CheckOutDate,
+        guests: Option<u8>,
+    ) -> BookingId {
+        // 関数コール時に送付されたNEARを取得
+        let deposit = env::attached_deposit();
+        let (booking_id, overpayment) = self.internal_book_room(
+            room_id,
+            env::predecessor_account_id(),
+            check_in_date,
+            check_out_date,
+            guests,
+            deposit,
+            None,
+        );
+        refund(overpayment);
+        booking_id
+    }
+}
+
+// ブロックのタイムスタンプから今日の日付（UTC）を取得する
+fn today() -> Date {
+    Date::from_timestamp(env::block_timestamp())
+}
+
+// 宿泊する夜の日付が今日から部屋の予約受付期間内にあるかを確認する
+// // 部屋の設定に関わらず、コントラクト全体の上限より先の予約は受け付けない
+fn is_within_horizon(room: &Room, config: &Config, night: Date, today: Date) -> bool {
+    let days_ahead = today.days_until(night);
+    let horizon_days = room
+        .booking_horizon_days
+        .min(config.max_booking_horizon_days);
+    0 <= days_ahead && days_ahead <= horizon_days as i64
+}
+
+// 場所の前後の空白を取り除き、連続する空白を1つにまとめる
+fn clean_location(location: &str) -> String {
+    let location = location.split_whitespace().collect::<Vec<_>>().join(" ");
+    assert!(!location.is_empty(), "ERR_INVALID_LOCATION");
+    location
+}
+
+// 場所を比較するためのキー
+// // `Tokyo`、` tokyo `、`TOKYO`は同じ場所として扱う
+fn normalize_location(location: &str) -> String {
+    location
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// 予約できる部屋一覧に表示する部屋のデータを作成する
+fn available_room(room_id: RoomId, room: Room) -> AvailableRoom {
+    AvailableRoom {
+        room_id,
+        owner_id: room.owner_id,
+        name: room.name,
+        beds: room.beds,
+        image: room.image,
+        description: room.description,
+        location: room.location,
+        price: room.price,
+        payment_token: room.payment_token,
+    }
+}
+
+// 部屋が検索条件に合うかを確認する
+// // `price_of_stay`は宿泊期間の料金
+fn matches_query(room: &Room, price_of_stay: Balance, query: &RoomQuery) -> bool {
+    if let Some(ref location) = query.location {
+        if normalize_location(&room.location) != normalize_location(location) {
+            return false;
+        }
+    }
+    if let Some(min_beds) = query.min_beds {
+        if room.beds < min_beds {
+            return false;
+        }
+    }
+    if let Some(min_price) = query.min_price {
+        if price_of_stay < min_price.0 {
+            return false;
+        }
+    }
+    if let Some(max_price) = query.max_price {
+        if price_of_stay > max_price.0 {
+            return false;
+        }
+    }
+    true
+}
+
+// `from_index`件目から最大`limit`件を取り出す
+fn paginate<T>(
+    items: impl Iterator<Item = T>,
+    from_index: Option<u64>,
+    limit: Option<u64>,
+) -> Vec<T> {
+    items
+        .skip(from_index.unwrap_or(0) as usize)
+        .take(limit.unwrap_or(DEFAULT_PAGE_LIMIT) as usize)
+        .collect()
+}
+
+// `amount`のうち、手数料率`platform_fee_bps`のプラットフォーム手数料（端数は切り捨てる）
+pub(crate) fn platform_fee(amount: u128, platform_fee_bps: u16) -> u128 {
+    amount * platform_fee_bps as u128 / MAX_FEE_BPS as u128
+}
+
+// 部屋のIDを発行した順に並べるためのキー
+// // IDは連番の数値のため、文字列ではなく数値として比較する
+fn room_id_order(room_id: &RoomId) -> u64 {
+    room_id.parse().expect("ERR_INVALID_ROOM_ID")
+}
+
+fn assert_valid_config(config: &Config) {
+    assert!(config.platform_fee_bps <= MAX_FEE_BPS, "ERR_INVALID_FEE");
+}
+
+// チェックインの`days_before`日前にキャンセルした時の返金率（%）を返す
+// // `refund_policy`は`days_before`の降順に並んでいる
+fn refund_percent(refund_policy: &[RefundRule], days_before: i64) -> u8 {
+    refund_policy
+        .iter()
+        .find(|rule| rule.days_before as i64 <= days_before)
+        .map_or(0, |rule| rule.refund_percent)
+}
+
+// 宿泊数を返す
+// // チェックアウト日はチェックイン日より後でなければならない
+fn nights_of_stay(check_in_date: Date, check_out_date: Date) -> u64 {
+    let nights = check_in_date.days_until(check_out_date);
+    assert!(nights > 0, "ERR_INVALID_STAY_PERIOD");
+    nights as u64
+}
+
+// 宿泊期間`[check_in_date, check_out_date)`が部屋の既存の予約と重ならないかを確認する
+fn is_free(room: &Room, check_in_date: Date, check_out_date: Date) -> bool {
+    room.booked_info.iter().all(|(booked_in, stay)| {
+        check_out_date <= *booked_in || stay.check_out_date <= check_in_date
+    })
+}
+
+// Private functions
+impl Contract {
+    // 空のコレクションで状態を作成する
+    fn with_config(admin_id: AccountId, config: Config) -> Self {
+        Self {
+            rooms_per_owner: LookupMap::new(b"m"),
+            rooms_by_id: UnorderedMap::new(b"r"),
+            bookings_per_guest: LookupMap::new(b"g"),
+            staff_per_owner: LookupMap::new(b"s"),
+            balance_per_owner: LookupMap::new(b"b"),
+            next_room_id: 0,
+            admin_id,
+            config,
+            storage_accounts: LookupMap::new(b"d"),
+            rooms_per_location: TreeMap::new(b"l"),
+            accrued_fees: 0,
+            bookings_by_id: LookupMap::new(b"k"),
+            next_booking_id: 0,
+            accepted_tokens: UnorderedSet::new(b"t"),
+            token_balance_per_owner: LookupMap::new(b"w"),
+            accrued_token_fees: LookupMap::new(b"f"),
+            booking_tokens: UnorderedSet::new(b"n"),
+            resale_prices: LookupMap::new(b"p"),
+            quarantined_bookings: UnorderedMap::new(b"q"),
+        }
+    }
+
+    // 部屋を予約し、予約のIDと宿泊料を超えて支払われた金額を返す
+    // // `deposit`は`payment_token`（`None`の場合はNEAR）で支払われた金額で、部屋の支払い方法と一致しなければならない
+    // // 超えて支払われた分の返金は呼び出し元が行う
+    pub(crate) fn internal_book_room(
+        &mut self,
+        room_id: RoomId,
+        guest_id: AccountId,
+        check_in_date: CheckInDate,
+        check_out_date: CheckOutDate,
+        guests: Option<u8>,
+        deposit: Balance,
+        payment_token: Option<AccountId>,
+    ) -> (BookingId, Balance) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert!(room.is_listed, "ERR_ROOM_IS_DELISTED");
+        assert!(
+            room.payment_token == payment_token,
+            "ERR_WRONG_PAYMENT_TOKEN"
+        );
+        let guests = number_of_guests(&room, guests);
+
+        // 宿泊料を見積もる前に日付を確認し、予約できない宿泊期間の各夜の料金を計算しない
+        nights_of_stay(check_in_date, check_out_date);
+        // 過去の日付や、予約受付期間より先の日付は予約できない
+        let today = today();
+        assert!(check_in_date >= today, "ERR_CHECK_IN_DATE_IN_THE_PAST");
+        assert!(
+            is_within_horizon(&room, &self.config, check_in_date, today),
+            "ERR_CHECK_IN_DATE_BEYOND_HORIZON"
+        );
+        // 宿泊期間の最後の夜（チェックアウト日の前日）も予約受付期間内になければならない
+        assert!(
+            is_within_horizon(&room, &self.config, check_out_date.add_days(-1), today),
+            "ERR_CHECK_OUT_DATE_BEYOND_HORIZON"
+        );
+        // 宿泊期間のいずれかの夜に既に予約が入っていたら予約できない
+        // // パニックした場合はトランザクションが取り消され、支払われたNEARやトークンは宿泊者に返金される
+        assert!(
+            is_free(&room, check_in_date, check_out_date),
+            "ERR_ALREADY_BOOKED"
+        );
+
+        // 料金ルールに従って、宿泊期間の宿泊料を見積もる
+        let total_price = price_of_stay(&room, check_in_date, check_out_date);
+        // 支払われた金額が`get_quote`で見積もった宿泊料に足りなければ予約できない
+        assert!(
+            deposit >= total_price,
+            "ERR_DEPOSIT_IS_INCORRECT: expected {}, received {}",
+            total_price,
+            deposit
+        );
+        let overpayment = deposit - total_price;
+
+        // 予約の記録を保存し、予約が入った日付, 宿泊者ID, チェックアウト日, 宿泊料を登録
+        // // 予約の保存に使用するストレージの料金は宿泊者が支払う
+        let initial_storage = env::storage_usage();
+        let booking_id = self.add_booking(Booking::new(
+            room_id.clone(),
+            guest_id.clone(),
+            check_in_date,
+            check_out_date,
+            guests,
+            U128(total_price),
+            env::block_timestamp(),
+            BookingStatus::Booked,
+        ));
+        self.mint_booking_token(&booking_id, &guest_id);
+        room.booked_info.insert(
+            check_in_date,
+            Stay {
+                booking_id: booking_id.clone(),
+                guest_id: guest_id.clone(),
+                check_out_date,
+                amount_paid: U128(total_price),
+                platform_fee_bps: self.config.platform_fee_bps,
+                storage_paid_by_contract: false,
+            },
+        );
+        self.rooms_by_id.insert(&room_id, &room);
+
+        // 宿泊者に予約データを保存
+        // // 宿泊料はチェックインするまでコントラクトが預かる
+        self.add_booking_to_guest(guest_id.clone(), room_id.clone(), check_in_date);
+        self.update_storage_usage(&guest_id, initial_storage);
+
+        HotelEvent::BookingCreated(vec![BookingCreatedData {
+            booking_id: &booking_id,
+            room_id: &room_id,
+            guest_id: &guest_id,
+            check_in_date,
+            check_out_date,
+            amount_paid: U128(total_price),
+        }])
+        .emit();
+        if overpayment > 0 {
+            HotelEvent::Refunded(vec![RefundedData {
+                booking_id: &booking_id,
+                room_id: &room_id,
+                guest_id: &guest_id,
+                check_in_date,
+                amount: U128(overpayment),
+            }])
+            .emit();
+        }
+        (booking_id, overpayment)
+    }
+
+    // 部屋と宿泊者が持つ予約データを削除し、予約のNFTを焼却する
+    // // 予約の保存に使用していたストレージの分は宿泊者に戻す
+    fn remove_stay(
+        &mut self,
+        room_id: &RoomId,
+        room: &mut Room,
+        check_in_date: CheckInDate,
+    ) -> Stay {
+        let initial_storage = env::storage_usage();
+        let stay = room
+            .booked_info
+            .remove(&check_in_date)
+            .expect("ERR_NOT_FOUND_DATE");
+        self.rooms_by_id.insert(room_id, room);
+        self.remove_booking_from_guest(stay.guest_id.clone(), room_id.clone(), check_in_date);
+        self.burn_booking_token(&stay.booking_id, &stay.guest_id);
+        if !stay.storage_paid_by_contract {
+            self.update_storage_usage(&stay.guest_id, initial_storage);
+        }
+        stay
+    }
+
+    // 場所にある部屋のIDを、部屋のIDの昇順に取得する
+    fn room_ids_at(&self, location: &str) -> Vec<RoomId> {
+        let mut room_ids = self
+            .rooms_per_location
+            .get(&normalize_location(location))
+            .unwrap_or_default();
+        room_ids.sort_by_key(room_id_order);
+        room_ids
+    }
+
+    pub(crate) fn add_room_to_location(&mut self, room_id: &RoomId, location: &str) {
+        let location = normalize_location(location);
+        let mut room_ids = self.rooms_per_location.get(&location).unwrap_or_default();
+        room_ids.push(room_id.clone());
+        self.rooms_per_location.insert(&location, &room_ids);
+    }
+
+    fn remove_room_from_location(&mut self, room_id: &RoomId, location: &str) {
+        let location = normalize_location(location);
+        let mut room_ids = self
+            .rooms_per_location
+            .get(&location)
+            .expect("ERR_NOT_FOUND_LOCATION");
+        room_ids.retain(|id| id != room_id);
+        // 部屋がなくなった場所は一覧から削除する
+        if room_ids.is_empty() {
+            self.rooms_per_location.remove(&location);
+        } else {
+            self.rooms_per_location.insert(&location, &room_ids);
+        }
+    }
+
+    // 関数をコールしたアカウントが管理者であることを確認する
+    fn assert_admin(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.admin_id,
+            "ERR_NOT_ADMIN"
+        );
+    }
+
+    fn set_listed(&mut self, room_id: RoomId, is_listed: bool) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+        room.is_listed = is_listed;
+        self.rooms_by_id.insert(&room_id, &room);
+    }
+
+    // 支払いが確定した宿泊料からプラットフォーム手数料を差し引き、残りをオーナーが出金できる金額に加算する
+    // // 予約した時点の手数料率`platform_fee_bps`を適用し、最小単位未満の端数は切り捨てる
+    fn settle_to_owner(
+        &mut self,
+        owner_id: &AccountId,
+        token_id: &Option<AccountId>,
+        amount: u128,
+        platform_fee_bps: u16,
+    ) {
+        let fee = platform_fee(amount, platform_fee_bps);
+        if fee > 0 {
+            let accrued_fees = self.accrued_fees_of(token_id);
+            self.set_accrued_fees(token_id, accrued_fees + fee);
+        }
+        self.release_to_owner(owner_id, token_id, amount - fee);
+    }
+
+    // 宿泊料を宿泊者へ返金する
+    // // 送金に失敗しても返金が失われないよう、`on_refund`で結果を確認する
+    fn refund_to_guest(
+        &self,
+        guest_id: AccountId,
+        payment_token: &Option<AccountId>,
+        amount: u128,
+    ) {
+        transfer(guest_id.clone(), payment_token, amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_ON_WITHDRAW)
+                .on_refund(guest_id, U128(amount), payment_token.clone()),
+        );
+    }
+
+    // オーナーが出金できる金額に加算する
+    pub(crate) fn release_to_owner(
+        &mut self,
+        owner_id: &AccountId,
+        token_id: &Option<AccountId>,
+        amount: u128,
+    ) {
+        if amount == 0 {
+            return;
+        }
+        let balance = self.balance_of(owner_id, token_id);
+        self.set_balance(owner_id, token_id, balance + amount);
+    }
+
+    // オーナーが`token_id`で出金できる金額（`None`の場合はNEAR）
+    fn balance_of(&self, owner_id: &AccountId, token_id: &Option<AccountId>) -> u128 {
+        match token_id {
+            None => self.balance_per_owner.get(owner_id),
+            Some(token_id) => self
+                .token_balance_per_owner
+                .get(&(owner_id.clone(), token_id.clone())),
+        }
+        .unwrap_or(0)
+    }
+
+    fn set_balance(&mut self, owner_id: &AccountId, token_id: &Option<AccountId>, balance: u128) {
+        match token_id {
+            None if balance == 0 => {
+                self.balance_per_owner.remove(owner_id);
+            }
+            None => {
+                self.balance_per_owner.insert(owner_id, &balance);
+            }
+            Some(token_id) => {
+                let key = (owner_id.clone(), token_id.clone());
+                if balance == 0 {
+                    self.token_balance_per_owner.remove(&key);
+                } else {
+                    self.token_balance_per_owner.insert(&key, &balance);
+                }
+            }
+        }
+    }
+
+    // 管理者が`token_id`で出金できるプラットフォーム手数料（`None`の場合はNEAR）
+    fn accrued_fees_of(&self, token_id: &Option<AccountId>) -> u128 {
+        match token_id {
+            None => self.accrued_fees,
+            Some(token_id) => self.accrued_token_fees.get(token_id).unwrap_or(0),
+        }
+    }
+
+    fn set_accrued_fees(&mut self, token_id: &Option<AccountId>, accrued_fees: u128) {
+        match token_id {
+            None => self.accrued_fees = accrued_fees,
+            Some(token_id) if accrued_fees == 0 => {
+                self.accrued_token_fees.remove(token_id);
+            }
+            Some(token_id) => {
+                self.accrued_token_fees.insert(token_id, &accrued_fees);
+            }
+        }
+    }
+
+    // 関数をコールしたアカウントが、部屋のオーナーまたはそのスタッフであることを確認する
+    // // 他のコントラクトを経由した呼び出しでも正しく判定できるよう`predecessor_account_id`を使用する
+    fn assert_owner_or_staff(&self, room_id: &RoomId) {
+        let room = self.rooms_by_id.get(room_id).expect("ERR_NOT_FOUND_ROOM");
+        let caller = env::predecessor_account_id();
+
+        if caller == room.owner_id {
+            return;
+        }
+        let staff = self.staff_per_owner.get(&room.owner_id).unwrap_or_default();
+        assert!(staff.contains(&caller), "ERR_NOT_OWNER_OR_STAFF");
+    }
+
+    // 予約データを宿泊者用に保存する
+    fn add_booking_to_guest(
+        &mut self,
+        guest_id: AccountId,
+        room_id: RoomId,
+        check_in_date: CheckInDate,
+    ) {
+        match self.bookings_per_guest.get(&guest_id) {
+            // 宿泊者が既に別の予約データを所有している時
+            Some(mut booked_date) => {
+                // 既存の予約データを上書きしない
+                assert!(
+                    booked_date.insert((room_id, check_in_date)),
+                    "ERR_ALREADY_BOOKED"
+                );
+                self.bookings_per_guest.insert(&guest_id, &booked_date);
+            }
+            // 初めて予約データを保存する時
+            None => {
+                let mut new_guest_date = HashSet::new();
+                new_guest_date.insert((room_id, check_in_date));
+                self.bookings_per_guest.insert(&guest_id, &new_guest_date);
+            }
+        }
+    }
+
+    // 宿泊者の持つ予約データから、宿泊済みのデータを削除する
+    fn remove_booking_from_guest(
+        &mut self,
+        guest_id: AccountId,
+        room_id: RoomId,
+        check_in_date: CheckInDate,
+    ) {
+        // 宿泊者が持っている予約データのsetを取得
+        let mut book_info = self
+            .bookings_per_guest
+            .get(&guest_id)
+            .expect("ERR_NOT_FOUND_GUEST");
+
+        assert!(
+            book_info.remove(&(room_id, check_in_date)),
+            "ERR_NOT_FOUND_BOOKED"
+        );
+
+        // 予約データが空になった場合、`bookings_per_guest`からゲストを削除する
+        if book_info.is_empty() {
+            self.bookings_per_guest.remove(&guest_id);
+        } else {
+            self.bookings_per_guest.insert(&guest_id, &book_info);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        catch_panic, date, new_contract, new_contract_with_config, timestamp, transfers,
+    };
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, PromiseResult, RuntimeFeesConfig, VMConfig};
+    // トランザクションを実行するテスト環境を設定
+    fn get_context(is_view: bool) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(1))
+            .signer_account_id(accounts(1))
+            // 予約する日付より前の日付をブロックのタイムスタンプに設定
+            .block_timestamp(timestamp("2221-12-25"))
+            // 使用するメソッドをbooleanで指定(viewメソッドはtrue, changeメソッドはfalse)
+            .is_view(is_view);
+        builder
+    }
+    #[test]
+    fn add_then_get_registered_rooms() {
+        let context = get_context(false);
+        testing_env!(context.build());
+        let mut contract = new_contract(&context);
+        contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        contract.add_room_to_owner(
+            "201".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 201 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        // add_room_to_owner関数をコールしたアカウントIDを取得
+        let owner_id = env::predecessor_account_id();
+
+        let all_rooms = contract.get_rooms_registered_by_owner(owner_id, None, None);
+        assert_eq!(all_rooms.len(), 2);
+    }
+
+    #[test]
+    fn charge_storage_to_predecessor() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+        let mut contract = new_contract(&context);
+        let storage_balance = contract.storage_balance_of(accounts(1));
+
+        // `bob`が署名していても、`danny`のコントラクトからのコールでは`danny`のストレージの残高を使用する
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        let rooms = contract.get_rooms_registered_by_owner(accounts(3), None, None);
+        assert_eq!(rooms[0].room_id, room_id);
+        assert_eq!(contract.storage_balance_of(accounts(1)), storage_balance);
+    }
+
+    #[test]
+    fn no_registered_room() {
+        let context = get_context(true);
+        testing_env!(context.build());
+        let contract = new_contract(&context);
+
+        let no_registered_room = contract.get_rooms_registered_by_owner(accounts(0), None, None);
+        assert_eq!(no_registered_room.len(), 0);
+    }
+
+    #[test]
+    fn add_then_get_available_rooms() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        contract.add_room_to_owner(
+            "101".to_string(),
             "test.img".to_string(),
             1,
             "This is 101 room".to_string(),
             "Tokyo".to_string(),
             U128(10),
+            None,
         );
         contract.add_room_to_owner(
             "201".to_string(),
@@ -477,11 +1811,17 @@ mod tests {
             "This is 201 room".to_string(),
             "Tokyo".to_string(),
             U128(10),
+            None,
         );
 
         // `get_available_rooms`をコールするアカウントを設定
-        testing_env!(context.signer_account_id(accounts(2)).build());
-        let available_rooms = contract.get_available_rooms("2222-01-01".to_string());
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+            .rooms;
         assert_eq!(available_rooms.len(), 2);
     }
 
@@ -489,9 +1829,11 @@ mod tests {
     fn no_available_room() {
         let context = get_context(true);
         testing_env!(context.build());
-        let contract = Contract::default();
+        let contract = new_contract(&context);
 
-        let available_rooms = contract.get_available_rooms("2222-01-01".to_string());
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+            .rooms;
         assert_eq!(available_rooms.len(), 0);
     }
 
@@ -507,8 +1849,8 @@ mod tests {
 
         testing_env!(context.build());
 
-        let owner_id = env::signer_account_id();
-        let mut contract = Contract::default();
+        let owner_id = env::predecessor_account_id();
+        let mut contract = new_contract(&context);
         contract.add_room_to_owner(
             "101".to_string(),
             "test.img".to_string(),
@@ -516,28 +1858,41 @@ mod tests {
             "This is 101 room".to_string(),
             "Tokyo".to_string(),
             U128(10),
+            None,
         );
 
         ///////////////////
         // CHECK BOOKING //
         ///////////////////
         // `get_available_rooms`と`book_room`をコールするアカウントを設定
-        testing_env!(context.signer_account_id(accounts(2)).build());
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
 
-        let check_in_date: String = "2222-01-01".to_string();
-        let available_rooms = contract.get_available_rooms(check_in_date.clone());
+        let check_in_date = date("2222-01-01");
+        let check_out_date = date("2222-01-02");
+        let available_rooms = contract
+            .get_available_rooms(check_in_date, check_out_date, None, None, None)
+            .rooms;
 
         // 予約を実行
-        contract.book_room(available_rooms[0].room_id.clone(), check_in_date.clone());
+        contract.book_room(
+            available_rooms[0].room_id.clone(),
+            check_in_date,
+            check_out_date,
+            None,
+        );
 
         // オーナー用の予約データの中身を確認
-        let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone());
+        let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone(), None, None);
         assert_eq!(booked_rooms.len(), 1);
         assert_eq!(booked_rooms[0].check_in_date, check_in_date);
+        assert_eq!(booked_rooms[0].check_out_date, check_out_date);
         assert_eq!(booked_rooms[0].guest_id, accounts(2));
 
         // 宿泊者用の予約データの中身を確認
-        let guest_booked_rooms = contract.get_booking_info_for_guest(accounts(2));
+        let guest_booked_rooms = contract.get_booking_info_for_guest(accounts(2), None, None);
         assert_eq!(guest_booked_rooms.len(), 1);
         assert_eq!(guest_booked_rooms[0].owner_id, owner_id);
 
@@ -545,33 +1900,1900 @@ mod tests {
         // CHECK CHANGE STATUS //
         /////////////////////////
         // 'change_status_to_stay'をコールするアカウントを部屋のオーナーに設定
-        testing_env!(context.signer_account_id(accounts(1)).build());
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(timestamp("2222-01-01"))
+            .build());
 
         // 部屋のステータスを確認
         let is_available = contract.is_available(booked_rooms[0].room_id.clone());
-        assert_eq!(is_available, true);
+        assert!(is_available);
 
         // 部屋のステータスを変更（Available -> Stay）
-        contract.change_status_to_stay(booked_rooms[0].room_id.clone(), check_in_date.clone());
-        let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone());
+        contract.change_status_to_stay(booked_rooms[0].room_id.clone(), check_in_date, accounts(2));
+        // チェックインした時点で宿泊料がオーナーの出金できる残高に移る
+        assert_eq!(contract.get_escrow_balance(owner_id.clone(), None), U128(0));
+        assert_eq!(
+            contract.get_withdrawable_balance(owner_id.clone(), None),
+            U128(10)
+        );
+        let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone(), None, None);
         assert_ne!(booked_rooms[0].status, UsageStatus::Available);
 
         // 再度ステータスを確認
         let is_available = contract.is_available(booked_rooms[0].room_id.clone());
-        assert_eq!(is_available, false);
+        assert!(!is_available);
 
         // 部屋のステータスを変更（Stay -> Available）
         contract.change_status_to_available(
             available_rooms[0].room_id.clone(),
-            check_in_date.clone(),
+            check_in_date,
             booked_rooms[0].guest_id.clone(),
         );
         // 予約データから削除されたかチェック
-        let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone());
+        let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone(), None, None);
         assert_eq!(booked_rooms.len(), 0);
 
         // 宿泊者の予約データから消えたかチェック
-        let guest_booked_info = contract.get_booking_info_for_guest(accounts(2));
+        let guest_booked_info = contract.get_booking_info_for_guest(accounts(2), None, None);
         assert_eq!(guest_booked_info.len(), 0);
     }
+
+    #[test]
+    fn book_room_for_multiple_nights() {
+        let mut context = get_context(false);
+
+        // 3泊分の宿泊料を設定
+        context.account_balance(30);
+        context.attached_deposit(30);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(room_id, date("2222-01-30"), date("2222-02-02"), None);
+
+        // 宿泊期間と重なる期間には予約できる部屋がない
+        let available_rooms = contract
+            .get_available_rooms(date("2222-02-01"), date("2222-02-05"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 0);
+
+        // チェックアウト日からは予約できる
+        let available_rooms = contract
+            .get_available_rooms(date("2222-02-02"), date("2222-02-05"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 1);
+
+        let guest_booked_rooms = contract.get_booking_info_for_guest(accounts(2), None, None);
+        assert_eq!(guest_booked_rooms[0].check_out_date, date("2222-02-02"));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ALREADY_BOOKED")]
+    fn book_overlapping_nights() {
+        let mut context = get_context(false);
+        context.account_balance(20);
+        context.attached_deposit(20);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-03"),
+            None,
+        );
+
+        // 2泊目が重なる予約は失敗する
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.book_room(room_id, date("2222-01-02"), date("2222-01-04"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CHECK_IN_DATE_IN_THE_PAST")]
+    fn book_room_in_the_past() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        // ブロックのタイムスタンプ（2221-12-25）より前の日付は予約できない
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(room_id, date("2221-12-24"), date("2221-12-25"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CHECK_IN_DATE_BEYOND_HORIZON")]
+    fn book_room_beyond_horizon() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        // 30日先まで予約を受け付ける部屋を登録
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            Some(30),
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        // 予約受付期間の最終日までは予約できる部屋として表示される
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-24"), date("2222-01-25"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 1);
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-25"), date("2222-01-26"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 0);
+
+        contract.book_room(room_id, date("2222-01-25"), date("2222-01-26"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CHECK_OUT_DATE_BEYOND_HORIZON")]
+    fn book_stay_ending_beyond_horizon() {
+        let mut context = get_context(false);
+        context.account_balance(20);
+        context.attached_deposit(20);
+        testing_env!(context.build());
+
+        // 30日先まで予約を受け付ける部屋を登録
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            Some(30),
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        // 最後の夜が予約受付期間の最終日の予約までは表示される
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-23"), date("2222-01-25"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 1);
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-24"), date("2222-01-26"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 0);
+
+        // チェックイン日が予約受付期間内でも、最後の夜が期間を超える予約はできない
+        contract.book_room(room_id, date("2222-01-24"), date("2222-01-26"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_CHECK_OUT_DATE_BEYOND_HORIZON")]
+    fn book_very_long_stay_beyond_horizon() {
+        let mut context = get_context(false);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            Some(30),
+        );
+
+        // 宿泊料を見積もる前に予約受付期間を確認するため、各夜の料金は計算されない
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(room_id, date("2221-12-25"), date("9999-12-31"), None);
+    }
+
+    // Room Owner   : bob(accounts(1))
+    // Booking Guest: charlie(accounts(2)), danny(accounts(3))
+    #[test]
+    fn refuse_double_booking() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        // charlieが先に予約する
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // dannyが同じ部屋・同じ日付を予約しようとすると失敗する
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .build());
+        assert_eq!(
+            catch_panic(|| {
+                contract.book_room(
+                    room_id.clone(),
+                    date("2222-01-01"),
+                    date("2222-01-02"),
+                    None,
+                )
+            }),
+            "ERR_ALREADY_BOOKED"
+        );
+        // dannyの宿泊料はオーナーへ送られない
+        assert!(near_sdk::test_utils::get_created_receipts().is_empty());
+
+        // charlieの予約は上書きされていない
+        let booked_rooms = contract.get_booking_info_for_owner(accounts(1), None, None);
+        assert_eq!(booked_rooms.len(), 1);
+        assert_eq!(booked_rooms[0].guest_id, accounts(2));
+        assert_eq!(
+            contract
+                .get_booking_info_for_guest(accounts(2), None, None)
+                .len(),
+            1
+        );
+        assert_eq!(
+            contract
+                .get_booking_info_for_guest(accounts(3), None, None)
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    fn book_two_rooms_on_same_date() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let mut room_ids = vec![];
+        for name in ["101", "201"] {
+            let room_id = contract.add_room_to_owner(
+                name.to_string(),
+                "test.img".to_string(),
+                1,
+                format!("This is {} room", name),
+                "Tokyo".to_string(),
+                U128(10),
+                None,
+            );
+            room_ids.push(room_id);
+        }
+
+        // 同じ宿泊者が同じチェックイン日に2部屋を予約できる
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        for room_id in room_ids {
+            contract.book_room(room_id, date("2222-01-01"), date("2222-01-02"), None);
+        }
+        assert_eq!(
+            contract
+                .get_booking_info_for_guest(accounts(2), None, None)
+                .len(),
+            2
+        );
+    }
+
+    // Room Owner   : bob(accounts(1))
+    // Booking Guest: charlie(accounts(2))
+    // Staff        : danny(accounts(3))
+    #[test]
+    fn staff_changes_status() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // オーナーがdannyをスタッフに登録
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.add_staff(accounts(3));
+        assert_eq!(
+            contract.get_staff(accounts(1), None, None),
+            vec![accounts(3)]
+        );
+
+        // スタッフが部屋のステータスを変更できる
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(timestamp("2222-01-01"))
+            .build());
+        contract.change_status_to_stay(room_id.clone(), date("2222-01-01"), accounts(2));
+        assert!(!contract.is_available(room_id.clone()));
+        contract.change_status_to_available(room_id, date("2222-01-01"), accounts(2));
+        assert_eq!(
+            contract
+                .get_booking_info_for_guest(accounts(2), None, None)
+                .len(),
+            0
+        );
+
+        // スタッフの登録を取り消す
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.remove_staff(accounts(3));
+        assert!(contract.get_staff(accounts(1), None, None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_OWNER_OR_STAFF")]
+    fn guest_cannot_change_status() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // オーナーが署名していても、別のアカウント（accounts(2)）を経由したコールは拒否される
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.change_status_to_available(room_id, date("2222-01-01"), accounts(2));
+    }
+
+    // Room Owner   : bob(accounts(1))
+    // Booking Guest: charlie(accounts(2))
+    #[test]
+    fn cancel_booking_with_refund_policy() {
+        let mut context = get_context(false);
+        context.account_balance(20);
+        context.attached_deposit(20);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        // 7日前までは全額、1日前までは半額を返金する
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_refund_policy(
+            room_id.clone(),
+            vec![
+                RefundRule {
+                    days_before: 1,
+                    refund_percent: 50,
+                },
+                RefundRule {
+                    days_before: 7,
+                    refund_percent: 100,
+                },
+            ],
+        );
+        assert_eq!(
+            contract.get_refund_policy(room_id.clone())[0].days_before,
+            7
+        );
+
+        // 2泊分を予約する（今日は2221-12-25）
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-03"),
+            None,
+        );
+        contract.book_room(
+            room_id.clone(),
+            date("2221-12-28"),
+            date("2221-12-30"),
+            None,
+        );
+        // 予約の時点では宿泊料はコントラクトが預かる
+        assert!(transfers().is_empty());
+        assert_eq!(contract.get_escrow_balance(accounts(1), None), U128(40));
+
+        // 7日前のキャンセルは全額返金
+        assert_eq!(
+            contract.cancel_booking(room_id.clone(), date("2222-01-01")),
+            U128(20)
+        );
+        assert_eq!(transfers(), vec![(accounts(2), 20)]);
+
+        // 3日前のキャンセルは半額返金、残りはオーナーの出金できる残高に移る
+        testing_env!(context.attached_deposit(0).build());
+        assert_eq!(
+            contract.cancel_booking(room_id.clone(), date("2221-12-28")),
+            U128(10)
+        );
+        assert_eq!(transfers(), vec![(accounts(2), 10)]);
+        assert_eq!(contract.get_escrow_balance(accounts(1), None), U128(0));
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(10)
+        );
+
+        // キャンセルした期間は再び予約できる
+        assert_eq!(
+            contract
+                .get_booking_info_for_guest(accounts(2), None, None)
+                .len(),
+            0
+        );
+        let available_rooms = contract
+            .get_available_rooms(date("2221-12-28"), date("2222-01-03"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_GUEST")]
+    fn cancel_booking_of_other_guest() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // 予約した宿泊者以外はキャンセルできない
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.cancel_booking(room_id, date("2222-01-01"));
+    }
+
+    #[test]
+    fn refund_failed_transfer_to_withdrawable_balance() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // オーナーがキャンセルし、宿泊料を全額返金する
+        testing_env!(context
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build());
+        contract.cancel_booking_by_owner(room_id, date("2222-01-01"));
+        assert_eq!(transfers(), vec![(accounts(2), 10)]);
+
+        // 送金に成功した場合は、宿泊者の残高は変わらない
+        testing_env!(
+            context.predecessor_account_id(accounts(0)).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        contract.on_refund(accounts(2), U128(10), None);
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(2), None),
+            U128(0)
+        );
+
+        // 送金に失敗した場合は、宿泊者が返金された金額を出金できる
+        testing_env!(
+            context.build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        contract.on_refund(accounts(2), U128(10), None);
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(2), None),
+            U128(10)
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.withdraw(None, None);
+        assert_eq!(transfers(), vec![(accounts(2), 10)]);
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(2), None),
+            U128(0)
+        );
+    }
+
+    #[test]
+    fn withdraw_released_balance() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("2222-01-01"))
+            .build());
+        contract.change_status_to_stay(room_id, date("2222-01-01"), accounts(2));
+
+        // 一部を出金した後、残りを全額出金する
+        contract.withdraw(Some(U128(4)), None);
+        assert_eq!(transfers(), vec![(accounts(1), 4)]);
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(6)
+        );
+
+        testing_env!(context.build());
+        contract.withdraw(None, None);
+        assert_eq!(transfers(), vec![(accounts(1), 6)]);
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_EARLY_TO_CHECK_IN")]
+    fn check_in_before_check_in_date() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // チェックイン日の前日にはチェックインできない
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("2221-12-31"))
+            .build());
+        contract.change_status_to_stay(room_id, date("2222-01-01"), accounts(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_BALANCE")]
+    fn withdraw_more_than_balance() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        contract.withdraw(Some(U128(1)), None);
+    }
+
+    // イベントのログを作成する
+    fn event_log(event: &str, data: &str) -> String {
+        format!(
+            r#"EVENT_JSON:{{"standard":"hotel_booking","version":"1.0.0","event":"{}","data":[{}]}}"#,
+            event, data
+        )
+    }
+
+    // NEP-171のイベントのログを作成する
+    fn nft_event_log(event: &str, data: &str) -> String {
+        format!(
+            r#"EVENT_JSON:{{"standard":"nep171","version":"1.0.0","event":"{}","data":[{}]}}"#,
+            event, data
+        )
+    }
+
+    #[test]
+    fn emit_booking_lifecycle_events() {
+        let mut context = get_context(false);
+        context.account_balance(100);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        testing_env!(context.build());
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "room_created",
+                r#"{"room_id":"0","owner_id":"bob","name":"101","location":"Tokyo","price":"10"}"#
+            )]
+        );
+
+        // 多く送付された分は返金される
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(15)
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                nft_event_log("nft_mint", r#"{"owner_id":"charlie","token_ids":["0"]}"#),
+                event_log(
+                    "booking_created",
+                    r#"{"booking_id":"0","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-01","check_out_date":"2222-01-02","amount_paid":"10"}"#
+                ),
+                event_log(
+                    "refunded",
+                    r#"{"booking_id":"0","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-01","amount":"5"}"#
+                ),
+            ]
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("2222-01-01"))
+            .build());
+        contract.change_status_to_stay(room_id.clone(), date("2222-01-01"), accounts(2));
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "checked_in",
+                r#"{"booking_id":"0","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-01"}"#
+            )]
+        );
+
+        testing_env!(context.build());
+        contract.change_status_to_available(room_id.clone(), date("2222-01-01"), accounts(2));
+        assert_eq!(
+            get_logs(),
+            vec![
+                nft_event_log("nft_burn", r#"{"owner_id":"charlie","token_ids":["0"]}"#),
+                event_log(
+                    "checked_out",
+                    r#"{"booking_id":"0","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-01"}"#
+                ),
+            ]
+        );
+
+        // 宿泊者がキャンセルした時、返金がなければ`refunded`は出力しない
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10)
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-02"),
+            date("2222-01-03"),
+            None,
+        );
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-03"),
+            date("2222-01-04"),
+            None,
+        );
+        testing_env!(context.attached_deposit(0).build());
+        contract.cancel_booking(room_id.clone(), date("2222-01-02"));
+        assert_eq!(
+            get_logs(),
+            vec![
+                nft_event_log("nft_burn", r#"{"owner_id":"charlie","token_ids":["1"]}"#),
+                event_log(
+                    "cancelled",
+                    r#"{"booking_id":"1","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-02","cancelled_by":"charlie"}"#
+                ),
+            ]
+        );
+
+        // オーナーがキャンセルした時は全額を返金する
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.cancel_booking_by_owner(room_id.clone(), date("2222-01-03"));
+        assert_eq!(
+            get_logs(),
+            vec![
+                nft_event_log("nft_burn", r#"{"owner_id":"charlie","token_ids":["2"]}"#),
+                event_log(
+                    "cancelled",
+                    r#"{"booking_id":"2","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-03","cancelled_by":"bob"}"#
+                ),
+                event_log(
+                    "refunded",
+                    r#"{"booking_id":"2","room_id":"0","guest_id":"charlie","check_in_date":"2222-01-03","amount":"10"}"#
+                ),
+            ]
+        );
+
+        testing_env!(context.build());
+        contract.remove_room(room_id);
+        assert_eq!(
+            get_logs(),
+            vec![event_log(
+                "room_removed",
+                r#"{"room_id":"0","owner_id":"bob"}"#
+            )]
+        );
+    }
+
+    #[test]
+    fn platform_fee_is_split_on_check_in() {
+        let mut context = get_context(false);
+        context.account_balance(200);
+        context.attached_deposit(100);
+        testing_env!(context.build());
+
+        let mut contract = new_contract_with_config(
+            &context,
+            Config {
+                platform_fee_bps: 1_000,
+                ..Config::default()
+            },
+        );
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(100),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-02"),
+            date("2222-01-03"),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("2222-01-01"))
+            .build());
+        // オーナーがキャンセルした予約からは手数料を差し引かない
+        contract.cancel_booking_by_owner(room_id.clone(), date("2222-01-02"));
+        assert_eq!(transfers(), vec![(accounts(2), 100)]);
+        assert_eq!(contract.get_accrued_fees(None), U128(0));
+
+        // チェックインした時点で、宿泊料の10%を手数料として差し引く
+        contract.change_status_to_stay(room_id, date("2222-01-01"), accounts(2));
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(90)
+        );
+        assert_eq!(contract.get_accrued_fees(None), U128(10));
+
+        // 管理者が手数料の一部を出金した後、残りを全額出金する
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.withdraw_fees(Some(U128(4)), accounts(3), None);
+        assert_eq!(transfers(), vec![(accounts(3), 4)]);
+        assert_eq!(contract.get_accrued_fees(None), U128(6));
+
+        testing_env!(context.build());
+        contract.withdraw_fees(None, accounts(3), None);
+        assert_eq!(transfers(), vec![(accounts(3), 6)]);
+        assert_eq!(contract.get_accrued_fees(None), U128(0));
+    }
+
+    #[test]
+    fn platform_fee_is_fixed_at_booking() {
+        let mut context = get_context(false);
+        context.account_balance(100);
+        context.attached_deposit(100);
+        testing_env!(context.build());
+
+        let mut contract = new_contract_with_config(
+            &context,
+            Config {
+                platform_fee_bps: 1_000,
+                ..Config::default()
+            },
+        );
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(100),
+            None,
+        );
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // 予約した後に手数料率を上げても、既存の予約には予約した時点の10%を適用する
+        testing_env!(context
+            .predecessor_account_id(accounts(0))
+            .attached_deposit(0)
+            .build());
+        contract.set_platform_fee_bps(MAX_FEE_BPS);
+
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(timestamp("2222-01-01"))
+            .build());
+        contract.change_status_to_stay(room_id, date("2222-01-01"), accounts(2));
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(90)
+        );
+        assert_eq!(contract.get_accrued_fees(None), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ADMIN")]
+    fn withdraw_fees_by_not_admin() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.withdraw_fees(None, accounts(1), None);
+    }
+
+    #[test]
+    fn update_room_keeps_booked_price() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // 料金とベッドの数を変更する
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build());
+        contract.update_room(room_id.clone(), None, Some(2), None, None, Some(U128(20)));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"hotel_booking","version":"1.0.0","event":"room_updated","data":[{{"room_id":"{}","owner_id":"{}","beds":2,"price":"20"}}]}}"#,
+                room_id,
+                accounts(1)
+            )]
+        );
+
+        let rooms = contract.get_rooms_registered_by_owner(accounts(1), None, None);
+        assert_eq!(rooms[0].beds, 2);
+        assert_eq!(rooms[0].price, U128(20));
+        assert_eq!(rooms[0].description, "This is 101 room");
+
+        // 既存の予約の宿泊料は変わらない
+        let booked_rooms = contract.get_booking_info_for_owner(accounts(1), None, None);
+        assert_eq!(booked_rooms[0].amount_paid, U128(10));
+        assert_eq!(contract.get_escrow_balance(accounts(1), None), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ROOM_OWNER")]
+    fn update_room_by_other_account() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        // accounts(0)は部屋のオーナーではない
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.update_room(room_id, None, None, None, None, Some(U128(1)));
+    }
+
+    #[test]
+    fn delist_then_relist_room() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.delist_room(room_id.clone());
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 0);
+        // オーナーの部屋一覧には残る
+        let rooms = contract.get_rooms_registered_by_owner(accounts(1), None, None);
+        assert_eq!(rooms.len(), 1);
+        assert!(!rooms[0].is_listed);
+
+        contract.relist_room(room_id);
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ROOM_IS_DELISTED")]
+    fn book_delisted_room() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.delist_room(room_id.clone());
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(room_id, date("2222-01-01"), date("2222-01-02"), None);
+    }
+
+    // Room Owner   : bob(accounts(1))
+    // Booking Guest: charlie(accounts(2))
+    #[test]
+    fn cancel_future_bookings_then_remove_room() {
+        let mut context = get_context(false);
+        context.account_balance(10);
+        context.attached_deposit(10);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2222-01-01"),
+            date("2222-01-02"),
+            None,
+        );
+
+        // 今日以降の予約がある間は削除できない
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .build());
+        assert_eq!(
+            catch_panic(|| contract.remove_room(room_id.clone())),
+            "ERR_ROOM_HAS_FUTURE_BOOKINGS"
+        );
+
+        // オーナーが予約をキャンセルすると、宿泊者に全額返金される
+        contract.cancel_booking_by_owner(room_id.clone(), date("2222-01-01"));
+        assert_eq!(transfers(), vec![(accounts(2), 10)]);
+        assert_eq!(
+            contract
+                .get_booking_info_for_guest(accounts(2), None, None)
+                .len(),
+            0
+        );
+
+        contract.remove_room(room_id);
+        assert_eq!(
+            contract
+                .get_rooms_registered_by_owner(accounts(1), None, None)
+                .len(),
+            0
+        );
+        let available_rooms = contract
+            .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+            .rooms;
+        assert_eq!(available_rooms.len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ROOM_HAS_FUTURE_BOOKINGS")]
+    fn remove_room_during_stay() {
+        let mut context = get_context(false);
+        context.account_balance(30);
+        context.attached_deposit(30);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.book_room(
+            room_id.clone(),
+            date("2221-12-25"),
+            date("2221-12-28"),
+            None,
+        );
+
+        // チェックイン日は過ぎたが、宿泊者がまだチェックインしていない宿泊期間中の予約は残る
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("2221-12-26"))
+            .build());
+        contract.remove_room(room_id);
+    }
+
+    #[test]
+    fn room_ids_are_unique() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        // 以前の`owner_id + name`形式では同じIDになってしまう組み合わせ
+        let first = contract.add_room_to_owner(
+            "1".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is room 1".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        let second = contract.add_room_to_owner(
+            "11".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is room 11".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        assert_ne!(first, second);
+        assert!(contract.exists(accounts(1), "1".to_string()));
+        assert!(!contract.exists(accounts(1), "2".to_string()));
+
+        let rooms = contract.get_rooms_registered_by_owner(accounts(1), None, None);
+        assert_eq!(rooms[0].room_id, first);
+        assert_eq!(rooms[1].room_id, second);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ROOM_NAME_ALREADY_EXISTS")]
+    fn add_room_with_same_name() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        for _ in 0..2 {
+            contract.add_room_to_owner(
+                "101".to_string(),
+                "test.img".to_string(),
+                1,
+                "This is 101 room".to_string(),
+                "Tokyo".to_string(),
+                U128(10),
+                None,
+            );
+        }
+    }
+
+    #[test]
+    fn admin_updates_config() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        assert_eq!(contract.get_admin(), accounts(0));
+
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+
+        contract.set_platform_fee_bps(250);
+        contract.set_max_rooms_per_owner(1);
+        contract.set_max_booking_horizon_days(10);
+        assert_eq!(
+            contract.get_config(),
+            Config {
+                platform_fee_bps: 250,
+                max_rooms_per_owner: 1,
+                max_booking_horizon_days: 10,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ADMIN")]
+    fn set_config_by_not_admin() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_platform_fee_bps(250);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_FEE")]
+    fn init_with_invalid_fee() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        Contract::new(
+            accounts(0),
+            Config {
+                platform_fee_bps: 10_001,
+                ..Config::default()
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_ROOMS")]
+    fn add_room_over_limit() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract_with_config(
+            &context,
+            Config {
+                max_rooms_per_owner: 1,
+                ..Config::default()
+            },
+        );
+        for name in ["101", "102"] {
+            contract.add_room_to_owner(
+                name.to_string(),
+                "test.img".to_string(),
+                1,
+                "This is a test room.".to_string(),
+                "Tokyo".to_string(),
+                U128(10),
+                None,
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_BOOKING_HORIZON_TOO_LONG")]
+    fn add_room_beyond_max_horizon() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract_with_config(
+            &context,
+            Config {
+                max_booking_horizon_days: 30,
+                ..Config::default()
+            },
+        );
+        contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            Some(60),
+        );
+    }
+
+    #[test]
+    fn lower_max_horizon_applies_to_existing_rooms() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+        assert_eq!(
+            contract
+                .get_available_rooms(date("2222-01-10"), date("2222-01-11"), None, None, None)
+                .rooms
+                .len(),
+            1
+        );
+
+        // 2221-12-25から10日後の2222-01-04までしか予約できない
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_max_booking_horizon_days(10);
+        assert_eq!(
+            contract
+                .get_available_rooms(date("2222-01-04"), date("2222-01-05"), None, None, None)
+                .rooms
+                .len(),
+            1
+        );
+        assert!(contract
+            .get_available_rooms(date("2222-01-10"), date("2222-01-11"), None, None, None)
+            .rooms
+            .is_empty());
+    }
+
+    #[test]
+    fn paginate_available_rooms_in_room_id_order() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        for name in ["101", "102", "103", "104"] {
+            contract.add_room_to_owner(
+                name.to_string(),
+                "test.img".to_string(),
+                1,
+                "This is a test room.".to_string(),
+                "Tokyo".to_string(),
+                U128(10),
+                None,
+            );
+        }
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.remove_room("1".to_string());
+
+        let (check_in_date, check_out_date) = (date("2222-01-01"), date("2222-01-02"));
+        let search = |from_index: Option<u64>, limit: Option<u64>| -> (Vec<RoomId>, Option<u64>) {
+            let page = contract.get_available_rooms(
+                check_in_date,
+                check_out_date,
+                None,
+                from_index,
+                limit,
+            );
+            let room_ids = page.rooms.into_iter().map(|room| room.room_id).collect();
+            (room_ids, page.next_index)
+        };
+        assert_eq!(
+            search(None, None),
+            (
+                vec!["0".to_string(), "2".to_string(), "3".to_string()],
+                None
+            )
+        );
+        // `limit`は確認する部屋の数のため、削除された部屋"1"も1件として数える
+        assert_eq!(search(None, Some(2)), (vec!["0".to_string()], Some(2)));
+        assert_eq!(
+            search(Some(2), Some(2)),
+            (vec!["2".to_string(), "3".to_string()], None)
+        );
+        assert_eq!(search(Some(4), None), (vec![], None));
+
+        // 条件に合う部屋がなくても、確認していない部屋が残っていれば続きを返す
+        let page = contract.get_available_rooms(
+            check_in_date,
+            check_out_date,
+            Some(RoomQuery {
+                min_beds: Some(2),
+                ..RoomQuery::default()
+            }),
+            None,
+            Some(3),
+        );
+        assert!(page.rooms.is_empty());
+        assert_eq!(page.next_index, Some(3));
+
+        let registered = contract.get_rooms_registered_by_owner(accounts(1), Some(1), Some(1));
+        assert_eq!(registered.len(), 1);
+        assert_eq!(registered[0].room_id, "2");
+    }
+
+    #[test]
+    fn paginate_bookings_in_check_in_date_order() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "Tokyo".to_string(),
+            U128(10),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(10)
+            .build());
+        for check_in_date in ["2222-01-03", "2222-01-01", "2222-01-02"] {
+            let check_in_date = date(check_in_date);
+            contract.book_room(
+                room_id.clone(),
+                check_in_date,
+                check_in_date.add_days(1),
+                None,
+            );
+        }
+
+        let dates = contract
+            .get_booking_info_for_guest(accounts(2), None, None)
+            .into_iter()
+            .map(|booked| booked.check_in_date.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(dates, vec!["2222-01-01", "2222-01-02", "2222-01-03"]);
+
+        let dates = contract
+            .get_booking_info_for_owner(accounts(1), Some(1), Some(1))
+            .into_iter()
+            .map(|booked| booked.check_in_date.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(dates, vec!["2222-01-02"]);
+
+        let guest_booked = contract.get_booking_info_for_guest(accounts(2), Some(2), Some(5));
+        assert_eq!(guest_booked.len(), 1);
+        assert_eq!(guest_booked[0].check_in_date, date("2222-01-03"));
+    }
+
+    #[test]
+    fn search_available_rooms_with_query() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        // 部屋の名前, 場所, ベッドの数, 1泊の料金
+        for (name, location, beds, price) in [
+            ("101", "Tokyo", 1, 10),
+            ("102", "Osaka", 2, 30),
+            ("103", "tokyo", 3, 20),
+        ] {
+            contract.add_room_to_owner(
+                name.to_string(),
+                "test.img".to_string(),
+                beds,
+                "This is a test room.".to_string(),
+                location.to_string(),
+                U128(price),
+                None,
+            );
+        }
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.add_room_to_owner(
+            "201".to_string(),
+            "test.img".to_string(),
+            2,
+            "This is a test room.".to_string(),
+            "Tokyo".to_string(),
+            U128(40),
+            None,
+        );
+
+        let search = |query: RoomQuery, limit: Option<u64>| -> Vec<RoomId> {
+            contract
+                .get_available_rooms(
+                    date("2222-01-01"),
+                    date("2222-01-02"),
+                    Some(query),
+                    None,
+                    limit,
+                )
+                .rooms
+                .into_iter()
+                .map(|room| room.room_id)
+                .collect()
+        };
+
+        assert_eq!(search(RoomQuery::default(), None), vec!["0", "1", "2", "3"]);
+        assert_eq!(
+            search(
+                RoomQuery {
+                    location: Some("TOKYO".to_string()),
+                    ..RoomQuery::default()
+                },
+                None
+            ),
+            vec!["0", "2", "3"]
+        );
+        assert_eq!(
+            search(
+                RoomQuery {
+                    min_beds: Some(2),
+                    ..RoomQuery::default()
+                },
+                None
+            ),
+            vec!["1", "2", "3"]
+        );
+        assert_eq!(
+            search(
+                RoomQuery {
+                    min_price: Some(U128(15)),
+                    max_price: Some(U128(35)),
+                    ..RoomQuery::default()
+                },
+                None
+            ),
+            vec!["1", "2"]
+        );
+        assert_eq!(
+            search(
+                RoomQuery {
+                    owner_id: Some(accounts(2)),
+                    ..RoomQuery::default()
+                },
+                None
+            ),
+            vec!["3"]
+        );
+        assert_eq!(
+            search(
+                RoomQuery {
+                    sort_by: Some(RoomSortBy::Newest),
+                    ..RoomQuery::default()
+                },
+                None
+            ),
+            vec!["3", "2", "1", "0"]
+        );
+        assert_eq!(
+            search(
+                RoomQuery {
+                    location: Some("tokyo".to_string()),
+                    sort_by: Some(RoomSortBy::Newest),
+                    ..RoomQuery::default()
+                },
+                Some(2)
+            ),
+            vec!["3", "2"]
+        );
+    }
+
+    #[test]
+    fn search_available_rooms_by_price_of_stay() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        for (name, price) in [("101", 10), ("102", 25)] {
+            contract.add_room_to_owner(
+                name.to_string(),
+                "test.img".to_string(),
+                1,
+                "This is a test room.".to_string(),
+                "Tokyo".to_string(),
+                U128(price),
+                None,
+            );
+        }
+        // 部屋"0"は2222-01-01の夜のみ1泊50にする
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.set_pricing_rules(
+            "0".to_string(),
+            PricingRules {
+                date_prices: vec![DatePrice {
+                    date: date("2222-01-01"),
+                    price: U128(50),
+                }],
+                ..PricingRules::default()
+            },
+        );
+
+        let search = |check_in_date: &str, query: RoomQuery| -> Vec<RoomId> {
+            contract
+                .get_available_rooms(
+                    date(check_in_date),
+                    date("2222-01-03"),
+                    Some(query),
+                    None,
+                    None,
+                )
+                .rooms
+                .into_iter()
+                .map(|room| room.room_id)
+                .collect()
+        };
+        let price_range = |min_price: Option<u128>, max_price: Option<u128>| RoomQuery {
+            min_price: min_price.map(U128),
+            max_price: max_price.map(U128),
+            ..RoomQuery::default()
+        };
+
+        // 宿泊期間の料金は、部屋"0"が50 + 10、部屋"1"が25 + 25
+        assert_eq!(search("2222-01-01", price_range(None, Some(55))), vec!["1"]);
+        assert_eq!(search("2222-01-01", price_range(Some(55), None)), vec!["0"]);
+        // 2222-01-02からの1泊では、部屋"0"の方が安い
+        assert_eq!(search("2222-01-02", price_range(None, Some(20))), vec!["0"]);
+        assert_eq!(search("2222-01-02", price_range(Some(20), None)), vec!["1"]);
+    }
+
+    #[test]
+    fn location_index_follows_room_changes() {
+        let mut context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        for (name, location) in [("101", "Tokyo"), ("102", "  tokyo "), ("103", "New  York")] {
+            contract.add_room_to_owner(
+                name.to_string(),
+                "test.img".to_string(),
+                1,
+                "This is a test room.".to_string(),
+                location.to_string(),
+                U128(10),
+                None,
+            );
+        }
+        let locations = |contract: &Contract| -> Vec<(String, u32)> {
+            contract
+                .get_locations(None, None)
+                .into_iter()
+                .map(|summary| (summary.location, summary.room_count))
+                .collect()
+        };
+        assert_eq!(
+            locations(&contract),
+            vec![("new york".to_string(), 1), ("tokyo".to_string(), 2)]
+        );
+        let rooms = contract.get_rooms_by_location("TOKYO".to_string(), None, None);
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[1].room_id, "1");
+        assert_eq!(rooms[1].location, "tokyo");
+
+        // 場所を変更した部屋は、新しい場所の部屋として扱う
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.update_room(
+            "0".to_string(),
+            None,
+            None,
+            None,
+            Some("Osaka".to_string()),
+            None,
+        );
+        assert_eq!(
+            locations(&contract),
+            vec![
+                ("new york".to_string(), 1),
+                ("osaka".to_string(), 1),
+                ("tokyo".to_string(), 1)
+            ]
+        );
+        // 場所の昇順にページごとに取得できる
+        assert_eq!(
+            contract.get_locations(Some(1), Some(1)),
+            vec![LocationSummary {
+                location: "osaka".to_string(),
+                room_count: 1,
+            }]
+        );
+
+        // 予約を受け付けていない部屋は表示しない
+        contract.delist_room("1".to_string());
+        assert!(contract
+            .get_rooms_by_location("tokyo".to_string(), None, None)
+            .is_empty());
+
+        // 部屋がなくなった場所は一覧に表示しない
+        contract.remove_room("1".to_string());
+        assert_eq!(
+            locations(&contract),
+            vec![("new york".to_string(), 1), ("osaka".to_string(), 1)]
+        );
+        assert_eq!(
+            contract
+                .get_available_rooms(
+                    date("2222-01-01"),
+                    date("2222-01-02"),
+                    Some(RoomQuery {
+                        location: Some("new york".to_string()),
+                        ..RoomQuery::default()
+                    }),
+                    None,
+                    None
+                )
+                .rooms
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_LOCATION")]
+    fn add_room_without_location() {
+        let context = get_context(false);
+        testing_env!(context.build());
+
+        let mut contract = new_contract(&context);
+        contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is 101 room".to_string(),
+            "  ".to_string(),
+            U128(10),
+            None,
+        );
+    }
 }
@@ -0,0 +1,586 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use std::collections::HashMap;
+
+use crate::*;
+
+// 状態のバージョンを保存するキー
+// // コントラクト本体とは別のキーに保存するため、データ構造が変わっても必ず読み込める
+const STATE_VERSION_KEY: &[u8] = b"VERSION";
+
+// 現在のデータ構造のバージョン
+// // `Contract`や`Room`のフィールドを変更した時は新しいバージョンを追加し、`migrate`に変換処理を追加する
+pub(crate) const CURRENT_STATE_VERSION: StateVersion = StateVersion::V1;
+
+// 保存されているデータ構造のバージョン
+#[derive(BorshDeserialize, BorshSerialize, PartialEq, Debug)]
+pub(crate) enum StateVersion {
+    // バージョンを保存していなかった、最初にデプロイされたデータ構造
+    V0,
+    // 現在のデータ構造
+    V1,
+}
+
+// 移行時に日付を変換できず、隔離された以前の予約
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuarantinedBooking {
+    room_id: RoomId,
+    // 以前のデータに保存されていた日付の文字列
+    check_in_date: String,
+    guest_id: AccountId,
+}
+
+// 保存されている状態のバージョンを取得する
+// // バージョンが保存されていない場合は最初のデータ構造とみなす
+pub(crate) fn read_state_version() -> StateVersion {
+    match env::storage_read(STATE_VERSION_KEY) {
+        Some(bytes) => StateVersion::try_from_slice(&bytes).expect("ERR_INVALID_STATE_VERSION"),
+        None => StateVersion::V0,
+    }
+}
+
+pub(crate) fn write_state_version(version: StateVersion) {
+    env::storage_write(
+        STATE_VERSION_KEY,
+        &version.try_to_vec().expect("ERR_SERIALIZE_STATE_VERSION"),
+    );
+}
+
+// 部屋のデータを`HashMap`で保持していた、以前のコントラクトのデータ構造
+// // 日付は文字列、部屋のIDは`オーナーのアカウントID + 部屋の名前`として保存されている
+#[derive(BorshDeserialize)]
+enum UsageStatusV0 {
+    Available,
+    Stay { check_in_date: String },
+}
+
+#[derive(BorshDeserialize)]
+struct RoomV0 {
+    name: String,
+    owner_id: AccountId,
+    image: String,
+    beds: u8,
+    description: String,
+    location: String,
+    price: U128,
+    status: UsageStatusV0,
+    // チェックイン日と宿泊者を紐付けて保持
+    booked_info: HashMap<String, AccountId>,
+}
+
+#[derive(BorshDeserialize)]
+struct ContractV0 {
+    // オーナーと所有する部屋のIDを紐付けて保持
+    // // 部屋のIDが変わるため、移行時に作り直す
+    _rooms_per_owner: LookupMap<AccountId, Vec<String>>,
+    rooms_by_id: HashMap<String, RoomV0>,
+    // 宿泊者とチェックイン日、部屋のIDを紐付けて保持
+    // // 部屋の`booked_info`と同じ内容のため、移行には使用しない
+    _bookings_per_guest: HashMap<AccountId, HashMap<String, String>>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 保存されているデータを、現在のデータ構造に移行する
+    // // 新しいコードをデプロイした直後に、コントラクトのアカウント自身がコールする
+    // // 既に現在のデータ構造で保存されている場合は何も変更しない
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = match read_state_version() {
+            StateVersion::V0 => from_v0(env::state_read().expect("ERR_NO_STATE_TO_MIGRATE")),
+            StateVersion::V1 => env::state_read().expect("ERR_NO_STATE_TO_MIGRATE"),
+        };
+        write_state_version(CURRENT_STATE_VERSION);
+        contract
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // 移行時に隔離された以前の予約の一覧を取得する
+    // // 部屋のIDと日付の文字列の昇順に、`from_index`件目から最大`limit`件を返す
+    pub fn get_quarantined_bookings(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<QuarantinedBooking> {
+        let mut bookings: Vec<((RoomId, String), AccountId)> =
+            self.quarantined_bookings.iter().collect();
+        bookings.sort_by(|((a_room, a_date), _), ((b_room, b_date), _)| {
+            (room_id_order(a_room), a_date).cmp(&(room_id_order(b_room), b_date))
+        });
+        let bookings = bookings
+            .into_iter()
+            .map(|((room_id, check_in_date), guest_id)| QuarantinedBooking {
+                room_id,
+                check_in_date,
+                guest_id,
+            });
+        paginate(bookings, from_index, limit)
+    }
+
+    // 隔離された以前の予約を、オーナーが正しいチェックイン日を指定して予約に戻す
+    // // `check_in_date`が`None`の場合は予約に戻さずに削除する
+    // // 以前のコントラクトでは宿泊料がオーナーへ送金済みのため、どちらの場合も返金は発生しない
+    pub fn resolve_quarantined_booking(
+        &mut self,
+        room_id: RoomId,
+        old_check_in_date: String,
+        check_in_date: Option<CheckInDate>,
+    ) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+        let guest_id = self
+            .quarantined_bookings
+            .remove(&(room_id.clone(), old_check_in_date))
+            .expect("ERR_NOT_FOUND_QUARANTINED_BOOKING");
+
+        // 移行されたデータと同じく、ストレージの料金はコントラクトが支払う
+        if let Some(check_in_date) = check_in_date {
+            assert!(
+                is_free(&room, check_in_date, check_in_date.add_days(1)),
+                "ERR_ALREADY_BOOKED"
+            );
+            let stay = add_migrated_booking(self, &room_id, &room.status, check_in_date, &guest_id);
+            room.booked_info.insert(check_in_date, stay);
+            self.rooms_by_id.insert(&room_id, &room);
+            self.add_booking_to_guest(guest_id, room_id, check_in_date);
+        }
+    }
+}
+
+// `V0`のデータ構造から変換する
+// // 以前のデータ構造には管理者がいないため、コントラクトのアカウントを管理者とし、既定の設定を使用する
+fn from_v0(old: ContractV0) -> Contract {
+    let mut contract = Contract::with_config(env::current_account_id(), Config::default());
+
+    // 実行ごとに同じIDが割り当てられるよう、以前のIDの順に移行する
+    let mut old_rooms: Vec<(String, RoomV0)> = old.rooms_by_id.into_iter().collect();
+    old_rooms.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    // `rooms_per_owner`は以前と同じキーで保存されているため、新しいIDの一覧で上書きする
+    let mut rooms_per_owner: HashMap<AccountId, Vec<RoomId>> = HashMap::new();
+    for (old_room_id, old_room) in old_rooms {
+        let room_id = contract.next_room_id.to_string();
+        contract.next_room_id += 1;
+
+        // 滞在中の日付を変換できない場合は、その予約と同じく隔離し、部屋は空室とする
+        let status = match old_room.status {
+            UsageStatusV0::Stay { check_in_date } => match check_in_date.parse() {
+                Ok(check_in_date) => UsageStatus::Stay { check_in_date },
+                Err(_) => UsageStatus::Available,
+            },
+            UsageStatusV0::Available => UsageStatus::Available,
+        };
+
+        // 以前のコントラクトは予約時に宿泊料をオーナーへ送金しているため、預かっている宿泊料はない
+        // // 日付を変換できない予約はデータを失わないよう隔離し、オーナーが後から日付を指定して戻す
+        let mut old_booked_info: Vec<(Date, AccountId)> = vec![];
+        for (check_in_date, guest_id) in old_room.booked_info {
+            match check_in_date.parse() {
+                Ok(date) => old_booked_info.push((date, guest_id)),
+                Err(err) => {
+                    env::log_str(&format!("{} (room {:?})", err, old_room_id));
+                    contract
+                        .quarantined_bookings
+                        .insert(&(room_id.clone(), check_in_date), &guest_id);
+                }
+            }
+        }
+        old_booked_info.sort_by_key(|(check_in_date, _)| *check_in_date);
+        let mut booked_info = HashMap::new();
+        for (check_in_date, guest_id) in old_booked_info {
+            let stay =
+                add_migrated_booking(&mut contract, &room_id, &status, check_in_date, &guest_id);
+            booked_info.insert(check_in_date, stay);
+            contract.add_booking_to_guest(guest_id, room_id.clone(), check_in_date);
+        }
+
+        rooms_per_owner
+            .entry(old_room.owner_id.clone())
+            .or_default()
+            .push(room_id.clone());
+
+        let room = Room {
+            name: old_room.name,
+            owner_id: old_room.owner_id,
+            image: old_room.image,
+            beds: old_room.beds,
+            description: old_room.description,
+            location: old_room.location,
+            price: old_room.price,
+            payment_token: None,
+            booking_horizon_days: DEFAULT_BOOKING_HORIZON_DAYS,
+            refund_policy: vec![],
+            pricing_rules: PricingRules::default(),
+            resale_policy: None,
+            is_listed: true,
+            status,
+            booked_info,
+            storage_paid_by_contract: true,
+        };
+        contract.add_room_to_location(&room_id, &room.location);
+        contract.rooms_by_id.insert(&room_id, &room);
+    }
+    for (owner_id, room_ids) in rooms_per_owner {
+        contract.rooms_per_owner.insert(&owner_id, &room_ids);
+    }
+
+    contract
+}
+
+// 以前の予約から予約の記録を作成し、予約のNFTを発行する
+// // 予約した日時と人数は記録されていないため0と1人とし、滞在中の予約はチェックイン済みとする
+fn add_migrated_booking(
+    contract: &mut Contract,
+    room_id: &RoomId,
+    room_status: &UsageStatus,
+    check_in_date: CheckInDate,
+    guest_id: &AccountId,
+) -> Stay {
+    let status = if *room_status == (UsageStatus::Stay { check_in_date }) {
+        BookingStatus::CheckedIn
+    } else {
+        BookingStatus::Booked
+    };
+    // 以前の予約は1泊のみのため、チェックアウト日はチェックイン日の翌日となる
+    let check_out_date = check_in_date.add_days(1);
+    let booking_id = contract.add_booking(Booking::new(
+        room_id.clone(),
+        guest_id.clone(),
+        check_in_date,
+        check_out_date,
+        1,
+        U128(0),
+        0,
+        status,
+    ));
+    contract.mint_booking_token(&booking_id, guest_id);
+    Stay {
+        booking_id,
+        guest_id: guest_id.clone(),
+        check_out_date,
+        amount_paid: U128(0),
+        platform_fee_bps: 0,
+        storage_paid_by_contract: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::get_context;
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+
+    // Borshの形式で値を書き込む
+    // // 以前のデータ構造の型を使わず、保存されているバイト列をそのまま再現する
+    fn write_u32(blob: &mut Vec<u8>, value: u32) {
+        blob.extend(value.to_le_bytes());
+    }
+
+    fn write_str(blob: &mut Vec<u8>, value: &str) {
+        write_u32(blob, value.len() as u32);
+        blob.extend(value.as_bytes());
+    }
+
+    // 以前のデータ構造の部屋のデータ
+    fn write_room_v0(
+        blob: &mut Vec<u8>,
+        name: &str,
+        owner_id: &str,
+        stay: Option<&str>,
+        booked_info: &[(&str, &str)],
+    ) {
+        write_str(blob, name);
+        write_str(blob, owner_id);
+        write_str(blob, "test.img");
+        blob.push(1);
+        write_str(blob, "This is a test room.");
+        write_str(blob, "Tokyo");
+        blob.extend(1_000_000_000_000_000_000_000_000u128.to_le_bytes());
+        match stay {
+            None => blob.push(0),
+            Some(check_in_date) => {
+                blob.push(1);
+                write_str(blob, check_in_date);
+            }
+        }
+        write_u32(blob, booked_info.len() as u32);
+        for (check_in_date, guest_id) in booked_info {
+            write_str(blob, check_in_date);
+            write_str(blob, guest_id);
+        }
+    }
+
+    // 最初にデプロイされたコントラクトが保存した状態を再現する
+    // // オーナーの`bob`が部屋`101`と`102`を登録し、`charlie`が`101`を予約してチェックインしている
+    fn write_state_v0() {
+        write_state_v0_with(Some("2222-01-01"), &["2222-01-01", "2222-02-01"]);
+    }
+
+    // `charlie`が`101`を`check_in_dates`の日付で予約し、`stay`の日付で滞在している状態を再現する
+    fn write_state_v0_with(stay: Option<&str>, check_in_dates: &[&str]) {
+        let owner_id = accounts(1).to_string();
+        let guest_id = accounts(2).to_string();
+        let old_ids = [format!("{}101", owner_id), format!("{}102", owner_id)];
+        let booked_info: Vec<(&str, &str)> = check_in_dates
+            .iter()
+            .map(|check_in_date| (*check_in_date, guest_id.as_str()))
+            .collect();
+
+        let mut blob = vec![];
+        // rooms_per_owner: LookupMap
+        write_str(&mut blob, "m");
+        // rooms_by_id: HashMap<RoomId, Room>
+        write_u32(&mut blob, 2);
+        write_str(&mut blob, &old_ids[0]);
+        write_room_v0(&mut blob, "101", &owner_id, stay, &booked_info);
+        write_str(&mut blob, &old_ids[1]);
+        write_room_v0(&mut blob, "102", &owner_id, None, &[]);
+        // bookings_per_guest: HashMap<AccountId, HashMap<CheckInDate, RoomId>>
+        write_u32(&mut blob, 1);
+        write_str(&mut blob, &guest_id);
+        write_u32(&mut blob, check_in_dates.len() as u32);
+        for check_in_date in check_in_dates {
+            write_str(&mut blob, check_in_date);
+            write_str(&mut blob, &old_ids[0]);
+        }
+        env::storage_write(b"STATE", &blob);
+
+        // LookupMapの要素は`prefix + Borshの形式のキー`に個別に保存されている
+        let mut key = b"m".to_vec();
+        write_str(&mut key, &owner_id);
+        let mut value = vec![];
+        write_u32(&mut value, 2);
+        write_str(&mut value, &old_ids[0]);
+        write_str(&mut value, &old_ids[1]);
+        env::storage_write(&key, &value);
+    }
+
+    // `migrate`はコントラクトのアカウント自身がコールする
+    fn setup() {
+        testing_env!(get_context().predecessor_account_id(accounts(0)).build());
+    }
+
+    // オーナーが部屋を登録できるよう、ストレージの支払いに使用するNEARを預ける
+    // // 以降はオーナーの`bob`がコールする
+    fn register_owner(contract: &mut Contract) {
+        testing_env!(get_context().attached_deposit(near_sdk::ONE_NEAR).build());
+        contract.storage_deposit(Some(accounts(1)), None);
+        testing_env!(get_context().build());
+    }
+
+    #[test]
+    fn migrate_state_v0() {
+        setup();
+        write_state_v0();
+        assert_eq!(read_state_version(), StateVersion::V0);
+
+        let contract = Contract::migrate();
+        assert_eq!(read_state_version(), CURRENT_STATE_VERSION);
+        assert_eq!(contract.get_admin(), accounts(0));
+
+        let rooms = contract.get_rooms_registered_by_owner(accounts(1), None, None);
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(rooms[0].room_id, "0");
+        assert_eq!(rooms[0].name, "101");
+        assert_eq!(rooms[0].price, U128(1_000_000_000_000_000_000_000_000));
+        assert_eq!(rooms[0].booking_horizon_days, DEFAULT_BOOKING_HORIZON_DAYS);
+        assert!(rooms[0].is_listed);
+        assert_eq!(
+            rooms[0].status,
+            UsageStatus::Stay {
+                check_in_date: "2222-01-01".parse().unwrap()
+            }
+        );
+        assert_eq!(rooms[1].room_id, "1");
+        assert_eq!(rooms[1].name, "102");
+        assert_eq!(rooms[1].status, UsageStatus::Available);
+        assert_eq!(
+            contract.get_locations(None, None),
+            vec![LocationSummary {
+                location: "tokyo".to_string(),
+                room_count: 2,
+            }]
+        );
+
+        let mut guest_booked = contract.get_booking_info_for_guest(accounts(2), None, None);
+        guest_booked.sort_by_key(|booked| booked.check_in_date);
+        assert_eq!(guest_booked.len(), 2);
+        assert_eq!(guest_booked[0].room_id, "0");
+        assert_eq!(guest_booked[0].check_out_date.to_string(), "2222-01-02");
+        assert_eq!(guest_booked[0].amount_paid, U128(0));
+        // 滞在中の予約はチェックイン済みとして記録される
+        assert_eq!(
+            contract
+                .get_booking(guest_booked[0].booking_id.clone())
+                .unwrap()
+                .status,
+            BookingStatus::CheckedIn
+        );
+        assert_eq!(guest_booked[1].check_in_date.to_string(), "2222-02-01");
+        // チェックアウト前の予約には予約のNFTが発行される
+        assert_eq!(contract.nft_total_supply(), U128(2));
+        assert_eq!(
+            get_logs()
+                .iter()
+                .filter(|log| log.contains(r#""event":"nft_mint""#))
+                .count(),
+            2
+        );
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+        assert_eq!(contract.get_resale_policy("0".to_string()), None);
+    }
+
+    #[test]
+    fn use_contract_after_migration() {
+        setup();
+        write_state_v0();
+        let contract = Contract::migrate();
+        env::state_write(&contract);
+
+        // 保存された状態を読み込み直し、新しい部屋を登録できる
+        let mut contract: Contract = env::state_read().unwrap();
+        register_owner(&mut contract);
+        let room_id = contract.add_room_to_owner(
+            "103".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is a test room.".to_string(),
+            "Tokyo".to_string(),
+            U128(1),
+            None,
+        );
+        assert_eq!(room_id, "2");
+        assert_eq!(
+            contract
+                .get_rooms_registered_by_owner(accounts(1), None, None)
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn remove_migrated_data_without_storage_credit() {
+        setup();
+        write_state_v0_with(None, &["2222-02-01"]);
+        let mut contract = Contract::migrate();
+        testing_env!(get_context().attached_deposit(near_sdk::ONE_NEAR).build());
+        contract.storage_deposit(Some(accounts(2)), None);
+        register_owner(&mut contract);
+        let owner_before = contract.storage_balance_of(accounts(1)).unwrap();
+        let guest_before = contract.storage_balance_of(accounts(2)).unwrap();
+
+        // コントラクトが支払った部屋や予約を削除しても、オーナーや宿泊者が使えるストレージは増えない
+        contract.remove_room("1".to_string());
+        assert_eq!(
+            contract.storage_balance_of(accounts(1)).unwrap(),
+            owner_before
+        );
+        testing_env!(get_context().predecessor_account_id(accounts(2)).build());
+        contract.cancel_booking("0".to_string(), "2222-02-01".parse().unwrap());
+        assert_eq!(
+            contract.storage_balance_of(accounts(2)).unwrap(),
+            guest_before
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ROOM_HAS_QUARANTINED_BOOKINGS")]
+    fn remove_room_with_quarantined_booking() {
+        setup();
+        write_state_v0_with(None, &["tomorrow"]);
+        let mut contract = Contract::migrate();
+        register_owner(&mut contract);
+
+        // 隔離された予約を残したまま部屋を削除すると、予約に戻せなくなる
+        contract.remove_room("0".to_string());
+    }
+
+    #[test]
+    fn migrate_current_state_is_noop() {
+        setup();
+        let mut contract = Contract::new(accounts(0), Config::default());
+        register_owner(&mut contract);
+        let room_id = contract.add_room_to_owner(
+            "101".to_string(),
+            "test.img".to_string(),
+            1,
+            "This is a test room.".to_string(),
+            "Tokyo".to_string(),
+            U128(1),
+            None,
+        );
+        env::state_write(&contract);
+
+        let contract = Contract::migrate();
+        let rooms = contract.get_rooms_registered_by_owner(accounts(1), None, None);
+        assert_eq!(rooms.len(), 1);
+        assert_eq!(rooms[0].room_id, room_id);
+        assert_eq!(read_state_version(), CURRENT_STATE_VERSION);
+    }
+
+    #[test]
+    fn quarantine_unparseable_dates() {
+        setup();
+        write_state_v0_with(Some("tomorrow"), &["tomorrow", "2222-1-1", "2222-02-01"]);
+
+        // 日付を変換できた予約のみ移行され、滞在中の日付を変換できない部屋は空室となる
+        let mut contract = Contract::migrate();
+        let rooms = contract.get_rooms_registered_by_owner(accounts(1), None, None);
+        assert_eq!(rooms[0].status, UsageStatus::Available);
+        let guest_booked = contract.get_booking_info_for_guest(accounts(2), None, None);
+        assert_eq!(guest_booked.len(), 1);
+        assert_eq!(guest_booked[0].check_in_date.to_string(), "2222-02-01");
+        assert_eq!(
+            contract.get_quarantined_bookings(None, None),
+            vec![
+                QuarantinedBooking {
+                    room_id: "0".to_string(),
+                    check_in_date: "2222-1-1".to_string(),
+                    guest_id: accounts(2),
+                },
+                QuarantinedBooking {
+                    room_id: "0".to_string(),
+                    check_in_date: "tomorrow".to_string(),
+                    guest_id: accounts(2),
+                },
+            ]
+        );
+
+        // オーナーが正しい日付を指定して予約に戻すか、削除する
+        testing_env!(get_context().build());
+        contract.resolve_quarantined_booking(
+            "0".to_string(),
+            "2222-1-1".to_string(),
+            Some("2222-01-01".parse().unwrap()),
+        );
+        contract.resolve_quarantined_booking("0".to_string(), "tomorrow".to_string(), None);
+        assert_eq!(contract.get_quarantined_bookings(None, None), vec![]);
+        let guest_booked = contract.get_booking_info_for_guest(accounts(2), None, None);
+        assert_eq!(guest_booked.len(), 2);
+        assert_eq!(guest_booked[0].check_in_date.to_string(), "2222-01-01");
+        assert_eq!(contract.nft_total_supply(), U128(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NO_STATE_TO_MIGRATE")]
+    fn migrate_without_state() {
+        setup();
+        Contract::migrate();
+    }
+}
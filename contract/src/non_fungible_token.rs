@@ -0,0 +1,807 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{
+    env, ext_contract, near_bindgen, serde_json, AccountId, Gas, PromiseOrValue, PromiseResult,
+};
+
+use crate::booking::BookingId;
+use crate::events::{NftBurnData, NftEvent, NftMintData, NftTransferData};
+use crate::*;
+
+// NEP-177: メタデータの規格のバージョン
+const NFT_METADATA_SPEC: &str = "nft-1.0.0";
+
+// 移転先のコントラクトの`nft_on_transfer`に使用するGAS
+const GAS_FOR_NFT_ON_TRANSFER: Gas = Gas(25_000_000_000_000);
+// 移転の結果を確認する`nft_resolve_transfer`に使用するGAS
+const GAS_FOR_NFT_RESOLVE_TRANSFER: Gas = Gas(15_000_000_000_000);
+
+// NEP-171: 予約のNFTを受け取るコントラクトが実装するメソッド
+// // トレイト自体は使用せず、`ext_contract`が生成する`ext_nft_receiver`からコールする
+#[allow(dead_code)]
+#[ext_contract(ext_nft_receiver)]
+trait NonFungibleTokenReceiver {
+    // NFTを移転元のアカウントに返す場合は`true`を返す
+    fn nft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        previous_owner_id: AccountId,
+        token_id: BookingId,
+        msg: String,
+    ) -> PromiseOrValue<bool>;
+}
+
+// NEP-177: 予約のNFTのコレクションのメタデータ
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NFTContractMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub base_uri: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<String>,
+}
+
+// NEP-177: 予約のNFTごとのメタデータ
+// // 日時は1970-01-01 00:00 UTCからの経過ミリ秒を文字列で表す
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenMetadata {
+    // 部屋の名前
+    pub title: Option<String>,
+    // 宿泊期間
+    pub description: Option<String>,
+    // 部屋の画像
+    pub media: Option<String>,
+    pub media_hash: Option<String>,
+    pub copies: Option<u64>,
+    // 予約した日時
+    pub issued_at: Option<String>,
+    // チェックアウト日
+    pub expires_at: Option<String>,
+    // チェックイン日
+    pub starts_at: Option<String>,
+    pub updated_at: Option<String>,
+    // `BookingTokenExtra`をJSONにした文字列
+    pub extra: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<String>,
+}
+
+// `TokenMetadata::extra`に保存する、予約した部屋とオーナー
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BookingTokenExtra {
+    pub room_id: RoomId,
+    pub owner_id: AccountId,
+    pub location: String,
+    pub check_in_date: CheckInDate,
+    pub check_out_date: CheckOutDate,
+}
+
+// NEP-171: 予約のNFT
+// // トークンのIDは予約のIDで、保有者がチェックインできる宿泊者となる
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Token {
+    pub token_id: BookingId,
+    pub owner_id: AccountId,
+    pub metadata: Option<TokenMetadata>,
+}
+
+// 予約のNFTは予約した時に発行され、チェックアウトやキャンセル、部屋の削除によって予約が終わると焼却される
+#[near_bindgen]
+impl Contract {
+    pub fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "NEAR Hotel Booking".to_string(),
+            symbol: "BOOKING".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    // 予約のNFTを取得する
+    // // 存在しない、または予約が終わって焼却された場合は`null`が返る
+    pub fn nft_token(&self, token_id: BookingId) -> Option<Token> {
+        if !self.booking_tokens.contains(&token_id) {
+            return None;
+        }
+        Some(self.booking_token(token_id))
+    }
+
+    // 予約のNFTを`receiver_id`へ移転する
+    // // チェックイン前の予約のみ移転でき、移転先のアカウントが宿泊者となる
+    // // 転売のルールが設定された部屋の予約は、`buy_resale_booking`でのみ移転できる
+    // // NFTの保有者が1yoctoNEARを添付してコールする
+    // // 1yoctoNEARを超えて添付した分は、移転先のアカウントのストレージの支払いに預ける
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: BookingId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        // 他のアカウントへの移転の承認（NEP-178）には対応しない
+        assert!(approval_id.is_none(), "ERR_APPROVAL_NOT_SUPPORTED");
+        self.assert_free_transfer_allowed(&token_id);
+        self.deposit_for_transfer(&receiver_id);
+        let sender_id = env::predecessor_account_id();
+        self.transfer_booking_token(&sender_id, &receiver_id, &token_id, memo.as_deref());
+    }
+
+    // 予約のNFTを`receiver_id`のコントラクトへ移転し、`nft_on_transfer`をコールする
+    // // `nft_on_transfer`が`true`を返した場合やパニックした場合は、`nft_resolve_transfer`で移転元のアカウントに戻す
+    // // 移転に成功した場合は`true`が返る
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: BookingId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        assert!(approval_id.is_none(), "ERR_APPROVAL_NOT_SUPPORTED");
+        self.assert_free_transfer_allowed(&token_id);
+        self.deposit_for_transfer(&receiver_id);
+        let sender_id = env::predecessor_account_id();
+        self.transfer_booking_token(&sender_id, &receiver_id, &token_id, memo.as_deref());
+
+        ext_nft_receiver::ext(receiver_id.clone())
+            .with_static_gas(GAS_FOR_NFT_ON_TRANSFER)
+            .nft_on_transfer(sender_id.clone(), sender_id.clone(), token_id.clone(), msg)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_NFT_RESOLVE_TRANSFER)
+                    .nft_resolve_transfer(sender_id, receiver_id, token_id, None),
+            )
+            .into()
+    }
+
+    // `nft_on_transfer`の結果を確認し、必要であれば予約のNFTを移転元のアカウントに戻す
+    // // 移転先のアカウントがNFTを保有したままの場合は`true`が返る
+    // // 既に移転先のアカウントが移転した、チェックインした、または予約が終わった場合は戻さない
+    #[private]
+    pub fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: BookingId,
+        #[allow(unused_variables)] approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        let must_return = match env::promise_result(0) {
+            PromiseResult::Successful(value) => serde_json::from_slice(&value).unwrap_or(true),
+            _ => true,
+        };
+        if !must_return {
+            return true;
+        }
+
+        let returnable = self.booking_tokens.contains(&token_id)
+            && self.bookings_by_id.get(&token_id).is_some_and(|booking| {
+                booking.guest_id == receiver_id && booking.status == BookingStatus::Booked
+            });
+        if !returnable {
+            return true;
+        }
+        self.return_booking_token(&receiver_id, &previous_owner_id, &token_id);
+        false
+    }
+
+    // NEP-181: 発行されている予約のNFTの数
+    pub fn nft_total_supply(&self) -> U128 {
+        U128(self.booking_tokens.len() as u128)
+    }
+
+    // NEP-181: 発行されている予約のNFTの一覧を取得する
+    // // `from_index`件目から最大`limit`件を返す
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<Token> {
+        let from_index = from_index.map(|index| index.0 as u64);
+        let tokens = self
+            .booking_tokens
+            .iter()
+            .map(|token_id| self.booking_token(token_id));
+        paginate(tokens, from_index, limit)
+    }
+
+    // NEP-181: アカウントが保有する予約のNFTの数
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        let bookings = self.bookings_per_guest.get(&account_id).unwrap_or_default();
+        U128(bookings.len() as u128)
+    }
+
+    // NEP-181: アカウントが保有する予約のNFTの一覧を取得する
+    // // チェックイン日の昇順に、`from_index`件目から最大`limit`件を返す
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let mut bookings: Vec<(RoomId, CheckInDate)> = self
+            .bookings_per_guest
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        bookings.sort_by_key(|(room_id, check_in_date)| (*check_in_date, room_id_order(room_id)));
+
+        let from_index = from_index.map(|index| index.0 as u64);
+        let tokens = bookings.into_iter().map(|(room_id, check_in_date)| {
+            let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+            let stay = room
+                .booked_info
+                .get(&check_in_date)
+                .expect("ERR_NOT_FOUND_DATE");
+            self.booking_token(stay.booking_id.clone())
+        });
+        paginate(tokens, from_index, limit)
+    }
+}
+
+impl Contract {
+    // 予約のNFTの移転に添付されたNEARを確認する
+    // // 1yoctoNEARを超えて添付された分は、移転先のアカウントのストレージの支払いに預ける
+    // // 移転先のアカウントがストレージを登録していなくても、添付したNEARで登録して移転できる
+    pub(crate) fn deposit_for_transfer(&mut self, receiver_id: &AccountId) {
+        let deposit = env::attached_deposit();
+        assert!(deposit >= 1, "ERR_REQUIRES_ATTACHED_DEPOSIT");
+        if deposit > 1 {
+            self.deposit_to_storage(receiver_id, deposit - 1);
+        }
+    }
+
+    // 予約のNFTを発行する
+    pub(crate) fn mint_booking_token(&mut self, booking_id: &BookingId, guest_id: &AccountId) {
+        self.booking_tokens.insert(booking_id);
+        NftEvent::Mint(vec![NftMintData {
+            owner_id: guest_id,
+            token_ids: vec![booking_id],
+        }])
+        .emit();
+    }
+
+    // 予約が終わった予約のNFTを焼却し、転売への出品を取り消す
+    pub(crate) fn burn_booking_token(&mut self, booking_id: &BookingId, guest_id: &AccountId) {
+        self.booking_tokens.remove(booking_id);
+        self.resale_prices.remove(booking_id);
+        NftEvent::Burn(vec![NftBurnData {
+            owner_id: guest_id,
+            token_ids: vec![booking_id],
+        }])
+        .emit();
+    }
+
+    // 予約のNFTを`sender_id`から`receiver_id`へ移転し、宿泊者を変更する
+    // // 予約の記録、部屋の`booked_info`と`bookings_per_guest`の宿泊者を全て書き換え、転売への出品は取り消す
+    // // 予約の保存に使用するストレージの料金は、移転元のアカウントに戻し、移転先のアカウントが支払う
+    pub(crate) fn transfer_booking_token(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        booking_id: &BookingId,
+        memo: Option<&str>,
+    ) {
+        assert!(
+            self.booking_tokens.contains(booking_id),
+            "ERR_NOT_FOUND_TOKEN"
+        );
+        let booking = self
+            .bookings_by_id
+            .get(booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        assert_eq!(&booking.guest_id, sender_id, "ERR_NOT_TOKEN_HOLDER");
+        assert_ne!(sender_id, receiver_id, "ERR_SAME_RECEIVER");
+        // チェックイン済みの予約は移転できない
+        assert_eq!(
+            booking.status,
+            BookingStatus::Booked,
+            "ERR_ALREADY_CHECKED_IN"
+        );
+        self.move_booking_token(sender_id, receiver_id, booking_id, memo, false);
+    }
+
+    // `nft_transfer_call`で移転した予約のNFTを、移転元のアカウントに戻す
+    // // `nft_resolve_transfer`は失敗できないため、移転元のアカウントが預けたNEARが足りない場合はコントラクトがストレージの料金を支払う
+    fn return_booking_token(
+        &mut self,
+        receiver_id: &AccountId,
+        previous_owner_id: &AccountId,
+        booking_id: &BookingId,
+    ) {
+        self.move_booking_token(receiver_id, previous_owner_id, booking_id, None, true);
+    }
+
+    // 予約のデータを`sender_id`から`receiver_id`の予約として保存し直す
+    // // `returning`が`true`の場合は、ストレージの料金が足りなくてもパニックしない
+    fn move_booking_token(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        booking_id: &BookingId,
+        memo: Option<&str>,
+        returning: bool,
+    ) {
+        let mut booking = self
+            .bookings_by_id
+            .get(booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        let room_id = booking.room_id.clone();
+        let check_in_date = booking.check_in_date;
+
+        // 予約のデータを一度削除し、移転元のアカウントが支払ったストレージの料金を全て戻す
+        // // 削除されるのは、部屋の`booked_info`、予約の記録、予約のNFT、`bookings_per_guest`と転売への出品
+        let initial_storage = env::storage_usage();
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        let mut stay = room
+            .booked_info
+            .remove(&check_in_date)
+            .expect("ERR_NOT_FOUND_DATE");
+        self.rooms_by_id.insert(&room_id, &room);
+        self.bookings_by_id.remove(booking_id);
+        self.booking_tokens.remove(booking_id);
+        self.remove_booking_from_guest(sender_id.clone(), room_id.clone(), check_in_date);
+        self.resale_prices.remove(booking_id);
+        // 移行した予約など、コントラクトが支払った分は移転元のアカウントに戻さない
+        if !stay.storage_paid_by_contract {
+            self.update_storage_usage(sender_id, initial_storage);
+        }
+
+        // 移転先のアカウントの予約として保存し直し、ストレージの料金を支払ってもらう
+        let initial_storage = env::storage_usage();
+        stay.guest_id = receiver_id.clone();
+        stay.storage_paid_by_contract = false;
+        room.booked_info.insert(check_in_date, stay);
+        self.rooms_by_id.insert(&room_id, &room);
+        booking.guest_id = receiver_id.clone();
+        self.bookings_by_id.insert(booking_id, &booking);
+        self.booking_tokens.insert(booking_id);
+        self.add_booking_to_guest(receiver_id.clone(), room_id.clone(), check_in_date);
+        if !returning {
+            self.update_storage_usage(receiver_id, initial_storage);
+        } else if !self.try_update_storage_usage(receiver_id, initial_storage) {
+            // 予約のサイズは変わらないため、保存し直してもストレージの使用量は増えない
+            room.booked_info
+                .get_mut(&check_in_date)
+                .expect("ERR_NOT_FOUND_DATE")
+                .storage_paid_by_contract = true;
+            self.rooms_by_id.insert(&room_id, &room);
+        }
+
+        NftEvent::Transfer(vec![NftTransferData {
+            old_owner_id: sender_id,
+            new_owner_id: receiver_id,
+            token_ids: vec![booking_id],
+            memo,
+        }])
+        .emit();
+    }
+
+    // 予約の記録と部屋のデータから、予約のNFTを作成する
+    fn booking_token(&self, booking_id: BookingId) -> Token {
+        let booking = self
+            .bookings_by_id
+            .get(&booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        let room = self
+            .rooms_by_id
+            .get(&booking.room_id)
+            .expect("ERR_NOT_FOUND_ROOM");
+        let extra = BookingTokenExtra {
+            room_id: booking.room_id,
+            owner_id: room.owner_id,
+            location: room.location,
+            check_in_date: booking.check_in_date,
+            check_out_date: booking.check_out_date,
+        };
+        let metadata = TokenMetadata {
+            title: Some(room.name),
+            description: Some(format!(
+                "Check-in: {}, Check-out: {}",
+                booking.check_in_date, booking.check_out_date
+            )),
+            media: Some(room.image),
+            media_hash: None,
+            copies: Some(1),
+            issued_at: Some((booking.created_at.0 / 1_000_000).to_string()),
+            expires_at: Some(booking.check_out_date.timestamp_ms().to_string()),
+            starts_at: Some(booking.check_in_date.timestamp_ms().to_string()),
+            updated_at: None,
+            extra: Some(serde_json::to_string(&extra).expect("ERR_SERIALIZE_EXTRA")),
+            reference: None,
+            reference_hash: None,
+        };
+        Token {
+            token_id: booking_id,
+            owner_id: booking.guest_id,
+            metadata: Some(metadata),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, RuntimeFeesConfig, VMConfig, ONE_NEAR};
+
+    // オーナーの`bob`が1泊1NEARの部屋を登録し、`charlie`が予約する
+    fn setup(context: &mut VMContextBuilder) -> (Contract, RoomId, BookingId) {
+        context.block_timestamp(2_000_000);
+        let mut contract = new_contract(context);
+        let room_id = add_room(&mut contract, "101", ONE_NEAR);
+        let booking_id = book(
+            context,
+            &mut contract,
+            &room_id,
+            accounts(2),
+            "1970-01-02",
+            ONE_NEAR,
+        );
+        (contract, room_id, booking_id)
+    }
+
+    fn transfer_to(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        token_id: &BookingId,
+    ) {
+        testing_env!(context
+            .signer_account_id(sender_id.clone())
+            .predecessor_account_id(sender_id)
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer(receiver_id, token_id.clone(), None, None);
+    }
+
+    #[test]
+    fn mint_booking_token() {
+        let mut context = get_context();
+        let (contract, room_id, booking_id) = setup(&mut context);
+
+        let token = contract.nft_token(booking_id.clone()).unwrap();
+        assert_eq!(token.owner_id, accounts(2));
+        assert_eq!(
+            token.metadata,
+            Some(TokenMetadata {
+                title: Some("101".to_string()),
+                description: Some("Check-in: 1970-01-02, Check-out: 1970-01-03".to_string()),
+                media: Some("test.img".to_string()),
+                media_hash: None,
+                copies: Some(1),
+                issued_at: Some("2".to_string()),
+                expires_at: Some("172800000".to_string()),
+                starts_at: Some("86400000".to_string()),
+                updated_at: None,
+                extra: Some(
+                    serde_json::to_string(&BookingTokenExtra {
+                        room_id,
+                        owner_id: accounts(1),
+                        location: "Tokyo".to_string(),
+                        check_in_date: date("1970-01-02"),
+                        check_out_date: date("1970-01-03"),
+                    })
+                    .unwrap()
+                ),
+                reference: None,
+                reference_hash: None,
+            })
+        );
+        assert_eq!(contract.nft_total_supply(), U128(1));
+        assert_eq!(contract.nft_tokens(None, None), vec![token]);
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(1));
+        assert_eq!(
+            contract.nft_tokens_for_owner(accounts(1), None, None),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn transfer_booking_token() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        transfer_to(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            accounts(3),
+            &booking_id,
+        );
+
+        // 移転先のアカウントが宿泊者となる
+        assert_eq!(
+            contract.nft_token(booking_id.clone()).unwrap().owner_id,
+            accounts(3)
+        );
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(0));
+        assert_eq!(
+            contract.nft_tokens_for_owner(accounts(3), None, None)[0].token_id,
+            booking_id
+        );
+        assert_eq!(
+            contract.get_booking(booking_id.clone()).unwrap().guest_id,
+            accounts(3)
+        );
+        assert!(contract
+            .get_booking_info_for_guest(accounts(2), None, None)
+            .is_empty());
+        assert_eq!(
+            contract.get_booking_info_for_guest(accounts(3), None, None)[0].booking_id,
+            booking_id
+        );
+
+        // チェックアウトすると、予約のNFTは焼却される
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("1970-01-02"))
+            .build());
+        contract.change_status_to_stay(room_id.clone(), date("1970-01-02"), accounts(3));
+        contract.change_status_to_available(room_id, date("1970-01-02"), accounts(3));
+        assert_eq!(contract.nft_token(booking_id), None);
+        assert_eq!(contract.nft_total_supply(), U128(0));
+    }
+
+    #[test]
+    fn move_storage_charge_with_booking_token() {
+        let mut context = get_context();
+        testing_env!(context.block_timestamp(2_000_000).build());
+        let mut contract = new_contract(&context);
+        let room_id = add_room(&mut contract, "101", ONE_NEAR);
+        let available = |contract: &Contract, account_id: AccountId| -> u128 {
+            contract.storage_balance_of(account_id).unwrap().available.0
+        };
+        let (charlie, danny) = (
+            available(&contract, accounts(2)),
+            available(&contract, accounts(3)),
+        );
+
+        let booking_id = book(
+            &mut context,
+            &mut contract,
+            &room_id,
+            accounts(2),
+            "1970-01-02",
+            ONE_NEAR,
+        );
+        assert!(available(&contract, accounts(2)) < charlie);
+
+        // 移転すると、予約の保存に使用するストレージの料金は全て移転先のアカウントに移る
+        transfer_to(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            accounts(3),
+            &booking_id,
+        );
+        assert_eq!(available(&contract, accounts(2)), charlie);
+        let danny_after_transfer = available(&contract, accounts(3));
+        assert!(danny_after_transfer < danny);
+
+        // キャンセルすると、移転先のアカウントが支払ったストレージの料金が戻る
+        // // 予約の記録は残るため、その分は移転先のアカウントが支払ったままとなる
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(0)
+            .build());
+        contract.cancel_booking(room_id, date("1970-01-02"));
+        assert_eq!(available(&contract, accounts(2)), charlie);
+        let danny_after_cancel = available(&contract, accounts(3));
+        assert!(danny_after_transfer < danny_after_cancel);
+        assert!(danny_after_cancel < danny);
+    }
+
+    #[test]
+    fn transfer_to_unregistered_account_with_storage_deposit() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        assert_eq!(contract.storage_balance_of(accounts(4)), None);
+
+        // 1yoctoNEARを超えて添付した分で、移転先のアカウントのストレージを登録する
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR + 1)
+            .build());
+        contract.nft_transfer(accounts(4), booking_id.clone(), None, None);
+        assert_eq!(
+            contract.nft_token(booking_id).unwrap().owner_id,
+            accounts(4)
+        );
+        let balance = contract.storage_balance_of(accounts(4)).unwrap();
+        assert_eq!(balance.total, U128(ONE_NEAR));
+        assert!(balance.available.0 < ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_STORAGE_NOT_REGISTERED")]
+    fn transfer_to_unregistered_account_without_storage_deposit() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        transfer_to(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            accounts(4),
+            &booking_id,
+        );
+    }
+
+    #[test]
+    fn resolve_transfer_call() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        let transfer_call = |context: &mut VMContextBuilder, contract: &mut Contract| {
+            testing_env!(context
+                .signer_account_id(accounts(2))
+                .predecessor_account_id(accounts(2))
+                .attached_deposit(1)
+                .build());
+            contract.nft_transfer_call(accounts(3), booking_id.clone(), None, None, "".to_string());
+            assert_eq!(
+                contract.nft_token(booking_id.clone()).unwrap().owner_id,
+                accounts(3)
+            );
+        };
+        // `nft_on_transfer`の結果を受け取り、`nft_resolve_transfer`がコールされる
+        let resolve = |context: &mut VMContextBuilder, contract: &mut Contract, result| -> bool {
+            testing_env!(
+                context
+                    .predecessor_account_id(accounts(0))
+                    .attached_deposit(0)
+                    .build(),
+                VMConfig::test(),
+                RuntimeFeesConfig::test(),
+                Default::default(),
+                vec![result],
+            );
+            contract.nft_resolve_transfer(accounts(2), accounts(3), booking_id.clone(), None)
+        };
+
+        // 移転先のコントラクトが`true`を返した場合は、移転元のアカウントに戻す
+        transfer_call(&mut context, &mut contract);
+        assert!(!resolve(
+            &mut context,
+            &mut contract,
+            PromiseResult::Successful(b"true".to_vec())
+        ));
+        assert_eq!(
+            contract.nft_token(booking_id.clone()).unwrap().owner_id,
+            accounts(2)
+        );
+
+        // 移転先のコントラクトがパニックした場合も、移転元のアカウントに戻す
+        transfer_call(&mut context, &mut contract);
+        assert!(!resolve(&mut context, &mut contract, PromiseResult::Failed));
+        assert_eq!(
+            contract.nft_token(booking_id.clone()).unwrap().owner_id,
+            accounts(2)
+        );
+
+        // 移転先のコントラクトが`false`を返した場合は、移転先のアカウントが保有する
+        transfer_call(&mut context, &mut contract);
+        assert!(resolve(
+            &mut context,
+            &mut contract,
+            PromiseResult::Successful(b"false".to_vec())
+        ));
+        assert_eq!(
+            contract.nft_token(booking_id.clone()).unwrap().owner_id,
+            accounts(3)
+        );
+        assert_eq!(contract.nft_supply_for_owner(accounts(2)), U128(0));
+    }
+
+    #[test]
+    fn resolve_transfer_call_without_storage_balance() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+        contract.nft_transfer_call(accounts(3), booking_id.clone(), None, None, "".to_string());
+        // 移転元のアカウントが、戻ってくる予約の分も含めてストレージの残高を全て引き出す
+        contract.storage_withdraw(None);
+        let balance = contract.storage_balance_of(accounts(2)).unwrap();
+
+        // ストレージの料金が足りなくても`nft_resolve_transfer`は失敗せず、コントラクトが支払って戻す
+        testing_env!(
+            context
+                .predecessor_account_id(accounts(0))
+                .attached_deposit(0)
+                .build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        assert!(!contract.nft_resolve_transfer(accounts(2), accounts(3), booking_id.clone(), None));
+        assert_eq!(
+            contract.nft_token(booking_id).unwrap().owner_id,
+            accounts(2)
+        );
+        assert_eq!(contract.storage_balance_of(accounts(2)).unwrap(), balance);
+
+        // コントラクトが支払った予約をキャンセルしても、移転元のアカウントの残高は増えない
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(0)
+            .build());
+        contract.cancel_booking(room_id, date("1970-01-02"));
+        assert_eq!(contract.storage_balance_of(accounts(2)).unwrap(), balance);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_TOKEN_HOLDER")]
+    fn transfer_booking_token_by_other_account() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        transfer_to(
+            &mut context,
+            &mut contract,
+            accounts(3),
+            accounts(4),
+            &booking_id,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_ALREADY_CHECKED_IN")]
+    fn transfer_checked_in_booking_token() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("1970-01-02"))
+            .build());
+        contract.change_status_to_stay(room_id, date("1970-01-02"), accounts(2));
+
+        transfer_to(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            accounts(3),
+            &booking_id,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_TOKEN_HOLDER")]
+    fn check_in_with_transferred_booking_token() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        transfer_to(
+            &mut context,
+            &mut contract,
+            accounts(2),
+            accounts(3),
+            &booking_id,
+        );
+
+        // 移転した後は、元の宿泊者はチェックインできない
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .attached_deposit(0)
+            .block_timestamp(timestamp("1970-01-02"))
+            .build());
+        contract.change_status_to_stay(room_id, date("1970-01-02"), accounts(2));
+    }
+}
@@ -0,0 +1,496 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen};
+
+use crate::*;
+
+// 曜日ごとの倍率を指定する場合の要素数（月曜日から日曜日まで）
+const DAYS_PER_WEEK: usize = 7;
+
+// オーナーが部屋ごとに設定する料金ルール
+// // 1泊の料金は、以下の優先順位で決まる
+// //   1. `date_prices`に宿泊する夜の日付があれば、その料金（曜日の倍率は適用しない）
+// //   2. `seasonal_prices`に宿泊する夜を含む期間があれば、その料金に曜日の倍率を適用する
+// //   3. どちらもなければ、部屋の`price`に曜日の倍率を適用する
+#[derive(
+    Serialize, Deserialize, BorshSerialize, BorshDeserialize, Default, Debug, PartialEq, Clone,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PricingRules {
+    // 曜日ごとの料金の倍率（%）
+    // // 月曜日から日曜日までの7つを指定する。空の場合は倍率を適用しない
+    // // 例: `[100, 100, 100, 100, 120, 150, 150]` 金曜日は2割増し、土曜日と日曜日は5割増し
+    #[serde(default)]
+    pub day_of_week_percent: Vec<u16>,
+    // 期間ごとの1泊の料金
+    #[serde(default)]
+    pub seasonal_prices: Vec<SeasonalPrice>,
+    // 日付ごとの1泊の料金
+    #[serde(default)]
+    pub date_prices: Vec<DatePrice>,
+}
+
+// `start_date`から`end_date`の前日までの夜に適用する1泊の料金
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SeasonalPrice {
+    pub start_date: Date,
+    pub end_date: Date,
+    pub price: U128,
+}
+
+// `date`の夜に適用する1泊の料金
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Debug, PartialEq, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DatePrice {
+    pub date: Date,
+    pub price: U128,
+}
+
+// 1泊ごとの料金
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NightlyPrice {
+    pub date: Date,
+    pub price: U128,
+}
+
+// 宿泊料の見積もり
+// // `book_room`では`total`を添付する（トークンで支払う部屋は`ft_transfer_call`で`total`以上を送付する）
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Quote {
+    pub room_id: RoomId,
+    pub check_in_date: CheckInDate,
+    pub check_out_date: CheckOutDate,
+    pub guests: u8,
+    pub nightly_prices: Vec<NightlyPrice>,
+    // 各夜の料金の合計
+    pub subtotal: U128,
+    // 宿泊料のうち、プラットフォーム手数料の分
+    // // オーナーへの支払いから差し引くため、宿泊者が`subtotal`とは別に支払う金額ではない
+    pub fees: U128,
+    // 税はコントラクトでは徴収しないため、常に0となる
+    pub taxes: U128,
+    // 宿泊者が支払う金額（`subtotal` + `taxes`）
+    pub total: U128,
+    // 宿泊料を支払うトークン（`None`の場合はNEAR）
+    pub payment_token: Option<AccountId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 部屋の料金ルールを設定する
+    // // 既存の予約の宿泊料には影響しない
+    pub fn set_pricing_rules(&mut self, room_id: RoomId, pricing_rules: PricingRules) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+
+        room.pricing_rules = validate_pricing_rules(pricing_rules);
+        let initial_storage = env::storage_usage();
+        self.rooms_by_id.insert(&room_id, &room);
+        self.update_storage_usage(&room.owner_id, initial_storage);
+    }
+
+    // 部屋の料金ルールを取得する
+    pub fn get_pricing_rules(&self, room_id: RoomId) -> PricingRules {
+        let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        room.pricing_rules
+    }
+
+    // 宿泊期間の各夜の料金の合計を見積もる
+    // // 各夜の料金の内訳は`get_quote`で取得する
+    pub fn quote_price(
+        &self,
+        room_id: RoomId,
+        check_in_date: CheckInDate,
+        check_out_date: CheckOutDate,
+    ) -> U128 {
+        let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        U128(price_of_stay(&room, check_in_date, check_out_date))
+    }
+
+    // 宿泊期間の宿泊料の内訳を見積もる
+    // // `guests`を指定しない場合は1人として扱う。ベッドの数より多い人数では宿泊できない
+    // // 料金は部屋ごとに決まり、宿泊する人数によって変わらない
+    pub fn get_quote(
+        &self,
+        room_id: RoomId,
+        check_in_date: CheckInDate,
+        check_out_date: CheckOutDate,
+        guests: Option<u8>,
+    ) -> Quote {
+        let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        let guests = number_of_guests(&room, guests);
+        let nightly_prices = nightly_prices(&room, check_in_date, check_out_date);
+        let subtotal = sum_of_nights(&nightly_prices);
+        let taxes = 0;
+        Quote {
+            room_id,
+            check_in_date,
+            check_out_date,
+            guests,
+            nightly_prices,
+            subtotal: U128(subtotal),
+            fees: U128(platform_fee(subtotal, self.config.platform_fee_bps)),
+            taxes: U128(taxes),
+            total: U128(subtotal + taxes),
+            payment_token: room.payment_token,
+        }
+    }
+}
+
+// 宿泊する人数を確認する
+// // 指定しない場合は1人として扱い、ベッドの数より多い人数では宿泊できない
+pub(crate) fn number_of_guests(room: &Room, guests: Option<u8>) -> u8 {
+    let guests = guests.unwrap_or(1);
+    assert!(guests > 0, "ERR_INVALID_GUESTS");
+    assert!(guests <= room.beds, "ERR_TOO_MANY_GUESTS");
+    guests
+}
+
+// 宿泊期間`[check_in_date, check_out_date)`の各夜の料金の合計を返す
+pub(crate) fn price_of_stay(room: &Room, check_in_date: Date, check_out_date: Date) -> u128 {
+    sum_of_nights(&nightly_prices(room, check_in_date, check_out_date))
+}
+
+// 宿泊期間`[check_in_date, check_out_date)`の各夜の料金を返す
+fn nightly_prices(room: &Room, check_in_date: Date, check_out_date: Date) -> Vec<NightlyPrice> {
+    let nights = nights_of_stay(check_in_date, check_out_date);
+    (0..nights as i64)
+        .map(|night| {
+            let date = check_in_date.add_days(night);
+            NightlyPrice {
+                date,
+                price: U128(price_of_night(room, date)),
+            }
+        })
+        .collect()
+}
+
+fn sum_of_nights(nightly_prices: &[NightlyPrice]) -> u128 {
+    nightly_prices.iter().fold(0u128, |total, night| {
+        total
+            .checked_add(night.price.0)
+            .expect("ERR_PRICE_OVERFLOW")
+    })
+}
+
+// `date`の夜の料金を返す
+// // 曜日の倍率を適用した結果、1yoctoNEAR未満の端数は切り捨てる
+fn price_of_night(room: &Room, date: Date) -> u128 {
+    let rules = &room.pricing_rules;
+    if let Some(date_price) = rules.date_prices.iter().find(|rule| rule.date == date) {
+        return date_price.price.0;
+    }
+    let price = rules
+        .seasonal_prices
+        .iter()
+        .find(|rule| rule.start_date <= date && date < rule.end_date)
+        .map_or(room.price.0, |rule| rule.price.0);
+    match rules.day_of_week_percent.get(date.weekday() as usize) {
+        Some(percent) => {
+            price
+                .checked_mul(*percent as u128)
+                .expect("ERR_PRICE_OVERFLOW")
+                / 100
+        }
+        None => price,
+    }
+}
+
+// 料金ルールを確認し、期間と日付の昇順に並べ替える
+// // 1つの夜に複数の料金が当てはまらないよう、期間の重なりや日付の重複は受け付けない
+fn validate_pricing_rules(pricing_rules: PricingRules) -> PricingRules {
+    let mut pricing_rules = pricing_rules;
+    assert!(
+        pricing_rules.day_of_week_percent.is_empty()
+            || pricing_rules.day_of_week_percent.len() == DAYS_PER_WEEK,
+        "ERR_INVALID_PRICING_RULES"
+    );
+
+    pricing_rules
+        .seasonal_prices
+        .sort_by_key(|rule| rule.start_date);
+    for rule in pricing_rules.seasonal_prices.iter() {
+        assert!(rule.start_date < rule.end_date, "ERR_INVALID_PRICING_RULES");
+    }
+    for rules in pricing_rules.seasonal_prices.windows(2) {
+        assert!(
+            rules[0].end_date <= rules[1].start_date,
+            "ERR_INVALID_PRICING_RULES"
+        );
+    }
+
+    pricing_rules.date_prices.sort_by_key(|rule| rule.date);
+    for rules in pricing_rules.date_prices.windows(2) {
+        assert!(rules[0].date < rules[1].date, "ERR_INVALID_PRICING_RULES");
+    }
+    pricing_rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    // オーナーの`bob`が1泊1NEARの部屋を登録する
+    fn setup(context: &mut VMContextBuilder) -> (Contract, RoomId) {
+        let mut contract = new_contract(context);
+        let room_id = add_room(&mut contract, "101", ONE_NEAR);
+        (contract, room_id)
+    }
+
+    #[test]
+    fn quote_with_pricing_rules() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+
+        // 1970-01-05は月曜日
+        assert_eq!(
+            contract.quote_price(room_id.clone(), date("1970-01-05"), date("1970-01-12")),
+            U128(7 * ONE_NEAR)
+        );
+
+        let pricing_rules = PricingRules {
+            day_of_week_percent: vec![100, 100, 100, 100, 100, 150, 150],
+            seasonal_prices: vec![SeasonalPrice {
+                start_date: date("1970-01-07"),
+                end_date: date("1970-01-09"),
+                price: U128(2 * ONE_NEAR),
+            }],
+            date_prices: vec![DatePrice {
+                date: date("1970-01-11"),
+                price: U128(3 * ONE_NEAR),
+            }],
+        };
+        contract.set_pricing_rules(room_id.clone(), pricing_rules.clone());
+        assert_eq!(contract.get_pricing_rules(room_id.clone()), pricing_rules);
+
+        // 月: 1, 火: 1, 水: 2（期間）, 木: 2（期間）, 金: 1, 土: 1.5（曜日）, 日: 3（日付）
+        assert_eq!(
+            contract.quote_price(room_id.clone(), date("1970-01-05"), date("1970-01-12")),
+            U128(11_500_000_000_000_000_000_000_000)
+        );
+        assert_eq!(
+            contract.quote_price(room_id, date("1970-01-10"), date("1970-01-11")),
+            U128(1_500_000_000_000_000_000_000_000)
+        );
+    }
+
+    #[test]
+    fn book_room_charges_quoted_price() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        contract.set_pricing_rules(
+            room_id.clone(),
+            PricingRules {
+                date_prices: vec![DatePrice {
+                    date: date("1970-01-02"),
+                    price: U128(2 * ONE_NEAR),
+                }],
+                ..Default::default()
+            },
+        );
+
+        let quote = contract.quote_price(room_id.clone(), date("1970-01-02"), date("1970-01-04"));
+        assert_eq!(quote, U128(3 * ONE_NEAR));
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(quote.0)
+            .build());
+        contract.book_room(room_id, date("1970-01-02"), date("1970-01-04"), None);
+
+        let guest_booked = contract.get_booking_info_for_guest(accounts(2), None, None);
+        assert_eq!(guest_booked[0].amount_paid, quote);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_DEPOSIT_IS_INCORRECT")]
+    fn book_room_with_base_price() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        contract.set_pricing_rules(
+            room_id.clone(),
+            PricingRules {
+                day_of_week_percent: vec![200; 7],
+                ..Default::default()
+            },
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        contract.book_room(room_id, date("1970-01-02"), date("1970-01-03"), None);
+    }
+
+    #[test]
+    fn get_quote_with_breakdown() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        contract.set_pricing_rules(
+            room_id.clone(),
+            PricingRules {
+                date_prices: vec![DatePrice {
+                    date: date("1970-01-03"),
+                    price: U128(2 * ONE_NEAR),
+                }],
+                ..Default::default()
+            },
+        );
+        // プラットフォーム手数料は10%とする
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.set_platform_fee_bps(1_000);
+
+        let quote = contract.get_quote(
+            room_id.clone(),
+            date("1970-01-02"),
+            date("1970-01-04"),
+            None,
+        );
+        assert_eq!(
+            quote,
+            Quote {
+                room_id,
+                check_in_date: date("1970-01-02"),
+                check_out_date: date("1970-01-04"),
+                guests: 1,
+                nightly_prices: vec![
+                    NightlyPrice {
+                        date: date("1970-01-02"),
+                        price: U128(ONE_NEAR),
+                    },
+                    NightlyPrice {
+                        date: date("1970-01-03"),
+                        price: U128(2 * ONE_NEAR),
+                    },
+                ],
+                subtotal: U128(3 * ONE_NEAR),
+                fees: U128(3 * ONE_NEAR / 10),
+                taxes: U128(0),
+                total: U128(3 * ONE_NEAR),
+                payment_token: None,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_GUESTS")]
+    fn get_quote_for_too_many_guests() {
+        let mut context = get_context();
+        let (contract, room_id) = setup(&mut context);
+        contract.get_quote(room_id, date("1970-01-02"), date("1970-01-03"), Some(2));
+    }
+
+    #[test]
+    fn book_room_for_guests() {
+        let mut context = get_context();
+        let (mut contract, _) = setup(&mut context);
+        let room_id = contract.add_room_to_owner(
+            "102".to_string(),
+            "test.img".to_string(),
+            2,
+            "This is a twin room.".to_string(),
+            "Tokyo".to_string(),
+            U128(ONE_NEAR),
+            None,
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        let booking_id =
+            contract.book_room(room_id, date("1970-01-02"), date("1970-01-03"), Some(2));
+        assert_eq!(contract.get_booking(booking_id).unwrap().guests, 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TOO_MANY_GUESTS")]
+    fn book_room_for_too_many_guests() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        contract.book_room(room_id, date("1970-01-02"), date("1970-01-03"), Some(2));
+    }
+
+    #[test]
+    fn book_room_refunds_overpayment() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(3 * ONE_NEAR)
+            .build());
+        contract.book_room(room_id, date("1970-01-02"), date("1970-01-03"), None);
+
+        let guest_booked = contract.get_booking_info_for_guest(accounts(2), None, None);
+        assert_eq!(guest_booked[0].amount_paid, U128(ONE_NEAR));
+        assert_eq!(transfers(), vec![(accounts(2), 2 * ONE_NEAR)]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "ERR_DEPOSIT_IS_INCORRECT: expected 2000000000000000000000000, received 1000000000000000000000000"
+    )]
+    fn book_room_with_too_little_deposit() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        contract.book_room(room_id, date("1970-01-02"), date("1970-01-04"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_INVALID_PRICING_RULES")]
+    fn set_overlapping_seasonal_prices() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        let season = |start_date: &str, end_date: &str| SeasonalPrice {
+            start_date: date(start_date),
+            end_date: date(end_date),
+            price: U128(2 * ONE_NEAR),
+        };
+        contract.set_pricing_rules(
+            room_id,
+            PricingRules {
+                seasonal_prices: vec![
+                    season("2222-08-01", "2222-09-01"),
+                    season("2222-07-01", "2222-08-02"),
+                ],
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ROOM_OWNER")]
+    fn set_pricing_rules_by_other_account() {
+        let mut context = get_context();
+        let (mut contract, room_id) = setup(&mut context);
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.set_pricing_rules(room_id, PricingRules::default());
+    }
+}
@@ -0,0 +1,415 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::booking::BookingId;
+use crate::events::{BookingResoldData, HotelEvent};
+use crate::*;
+
+// 予約の転売のルール
+// // 転売価格は支払われた宿泊料の`max_price_percent`%まで、転売されるたびに転売価格の`royalty_percent`%をオーナーが受け取る
+#[derive(Serialize, Deserialize, Debug, BorshSerialize, BorshDeserialize, PartialEq, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ResalePolicy {
+    pub max_price_percent: u16,
+    pub royalty_percent: u8,
+}
+
+#[near_bindgen]
+impl Contract {
+    // 部屋の予約の転売のルールを設定する
+    // // `None`の場合は転売を受け付けない。既に出品されている予約には、購入時のルールが適用される
+    pub fn set_resale_policy(&mut self, room_id: RoomId, resale_policy: Option<ResalePolicy>) {
+        let mut room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        assert_eq!(
+            env::predecessor_account_id(),
+            room.owner_id,
+            "ERR_NOT_ROOM_OWNER"
+        );
+        if let Some(ref resale_policy) = resale_policy {
+            assert!(
+                resale_policy.royalty_percent <= 100,
+                "ERR_INVALID_ROYALTY_PERCENT"
+            );
+        }
+
+        room.resale_policy = resale_policy;
+        let initial_storage = env::storage_usage();
+        self.rooms_by_id.insert(&room_id, &room);
+        self.update_storage_usage(&room.owner_id, initial_storage);
+    }
+
+    pub fn get_resale_policy(&self, room_id: RoomId) -> Option<ResalePolicy> {
+        let room = self.rooms_by_id.get(&room_id).expect("ERR_NOT_FOUND_ROOM");
+        room.resale_policy
+    }
+
+    // 予約を`new_guest_id`へ無償で譲渡する
+    // // 予約のNFTの`nft_transfer`と同じく、宿泊者が1yoctoNEARを添付してコールする
+    // // 1yoctoNEARを超えて添付した分は、`new_guest_id`のストレージの支払いに預ける
+    // // 転売のルールが設定された部屋の予約は譲渡できず、`buy_resale_booking`で購入してもらう
+    #[payable]
+    pub fn transfer_booking(&mut self, booking_id: BookingId, new_guest_id: AccountId) {
+        self.assert_free_transfer_allowed(&booking_id);
+        self.deposit_for_transfer(&new_guest_id);
+        let guest_id = env::predecessor_account_id();
+        self.transfer_booking_token(&guest_id, &new_guest_id, &booking_id, None);
+    }
+
+    // 予約を`price`で転売に出す
+    // // 既に出品している場合は価格を変更する。NEARで支払う部屋の予約のみ転売できる
+    pub fn list_booking_for_resale(&mut self, booking_id: BookingId, price: U128) {
+        let guest_id = env::predecessor_account_id();
+        let booking = self
+            .bookings_by_id
+            .get(&booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        assert_eq!(booking.guest_id, guest_id, "ERR_NOT_TOKEN_HOLDER");
+        assert_eq!(
+            booking.status,
+            BookingStatus::Booked,
+            "ERR_ALREADY_CHECKED_IN"
+        );
+        let room = self
+            .rooms_by_id
+            .get(&booking.room_id)
+            .expect("ERR_NOT_FOUND_ROOM");
+        assert!(room.payment_token.is_none(), "ERR_RESALE_NOT_SUPPORTED");
+        let resale_policy = room.resale_policy.expect("ERR_RESALE_NOT_ALLOWED");
+        assert_resale_price(&booking, &resale_policy, price.0);
+
+        let initial_storage = env::storage_usage();
+        self.resale_prices.insert(&booking_id, &price.0);
+        self.update_storage_usage(&guest_id, initial_storage);
+    }
+
+    // 転売への出品を取り消す
+    pub fn cancel_resale(&mut self, booking_id: BookingId) {
+        let guest_id = env::predecessor_account_id();
+        let booking = self
+            .bookings_by_id
+            .get(&booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        assert_eq!(booking.guest_id, guest_id, "ERR_NOT_TOKEN_HOLDER");
+
+        let initial_storage = env::storage_usage();
+        assert!(
+            self.resale_prices.remove(&booking_id).is_some(),
+            "ERR_NOT_LISTED_FOR_RESALE"
+        );
+        self.update_storage_usage(&guest_id, initial_storage);
+    }
+
+    // 予約の転売価格を取得する
+    // // 出品されていない場合は`null`が返る
+    pub fn get_resale_price(&self, booking_id: BookingId) -> Option<U128> {
+        self.resale_prices.get(&booking_id).map(U128)
+    }
+
+    // 転売に出された予約を購入する
+    // // 転売価格からロイヤリティを差し引いた額は出品者が、ロイヤリティはオーナーが出金できる金額に加算する
+    // // 出品者への送金が失敗しても代金が失われないよう、出品者は`withdraw`で出金する
+    // // 多く送付された分は購入者に返金する
+    #[payable]
+    pub fn buy_resale_booking(&mut self, booking_id: BookingId) {
+        let buyer_id = env::predecessor_account_id();
+        let price = self
+            .resale_prices
+            .get(&booking_id)
+            .expect("ERR_NOT_LISTED_FOR_RESALE");
+        let deposit = env::attached_deposit();
+        assert!(
+            deposit >= price,
+            "ERR_DEPOSIT_IS_INCORRECT: expected {}, received {}",
+            price,
+            deposit
+        );
+        let booking = self
+            .bookings_by_id
+            .get(&booking_id)
+            .expect("ERR_NOT_FOUND_BOOKING");
+        let room = self
+            .rooms_by_id
+            .get(&booking.room_id)
+            .expect("ERR_NOT_FOUND_ROOM");
+        // 出品した後に転売ルールが変更された場合も、現在のルールの上限を超える価格では購入できない
+        let resale_policy = room.resale_policy.expect("ERR_RESALE_NOT_ALLOWED");
+        assert_resale_price(&booking, &resale_policy, price);
+        let royalty = price * resale_policy.royalty_percent as u128 / 100;
+        let seller_id = booking.guest_id;
+
+        // 予約を購入者へ移転する（出品も取り消される）
+        self.transfer_booking_token(&seller_id, &buyer_id, &booking_id, None);
+        self.release_to_owner(&room.owner_id, &None, royalty);
+        self.release_to_owner(&seller_id, &None, price - royalty);
+
+        HotelEvent::BookingResold(vec![BookingResoldData {
+            booking_id: &booking_id,
+            room_id: &booking.room_id,
+            seller_id: &seller_id,
+            buyer_id: &buyer_id,
+            price: U128(price),
+            royalty: U128(royalty),
+        }])
+        .emit();
+        refund(deposit - price);
+    }
+}
+
+impl Contract {
+    // 転売のルールが設定された部屋の予約は、転売を通さずに移転できない
+    // // 無償で移転し、転売価格の上限やオーナーのロイヤリティを回避できないようにする
+    pub(crate) fn assert_free_transfer_allowed(&self, booking_id: &BookingId) {
+        let room = self
+            .bookings_by_id
+            .get(booking_id)
+            .and_then(|booking| self.rooms_by_id.get(&booking.room_id));
+        if let Some(room) = room {
+            assert!(room.resale_policy.is_none(), "ERR_TRANSFER_REQUIRES_RESALE");
+        }
+    }
+}
+
+// 転売価格が、転売ルールの上限（宿泊料の`max_price_percent`%）以下であることを確認する
+fn assert_resale_price(booking: &Booking, resale_policy: &ResalePolicy, price: Balance) {
+    let max_price = booking.amount_paid.0 * resale_policy.max_price_percent as u128 / 100;
+    assert!(
+        price <= max_price,
+        "ERR_RESALE_PRICE_TOO_HIGH: max {}, received {}",
+        max_price,
+        price
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    // オーナーの`bob`が1泊100yoctoNEARの部屋を登録し、`charlie`が予約する
+    // // 転売価格は宿泊料の120%まで、ロイヤリティは10%とする
+    fn setup(context: &mut VMContextBuilder) -> (Contract, RoomId, BookingId) {
+        let mut contract = new_contract(context);
+        let room_id = add_room(&mut contract, "101", 100);
+        contract.set_resale_policy(
+            room_id.clone(),
+            Some(ResalePolicy {
+                max_price_percent: 120,
+                royalty_percent: 10,
+            }),
+        );
+
+        let booking_id = book(
+            context,
+            &mut contract,
+            &room_id,
+            accounts(2),
+            "1970-01-02",
+            100,
+        );
+        testing_env!(context.attached_deposit(0).build());
+        (contract, room_id, booking_id)
+    }
+
+    // オーナーが転売のルールを取り消し、予約を無償で譲渡できるようにする
+    // // 以降は宿泊者の`charlie`が1yoctoNEARを添付してコールする
+    fn allow_free_transfer(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        room_id: &RoomId,
+    ) {
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_resale_policy(room_id.clone(), None);
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(1)
+            .build());
+    }
+
+    #[test]
+    fn transfer_booking() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        allow_free_transfer(&mut context, &mut contract, &room_id);
+        contract.transfer_booking(booking_id.clone(), accounts(3));
+
+        let owner_booked = contract.get_booking_info_for_owner(accounts(1), None, None);
+        assert_eq!(owner_booked[0].guest_id, accounts(3));
+        assert!(contract
+            .get_booking_info_for_guest(accounts(2), None, None)
+            .is_empty());
+        assert_eq!(
+            contract.get_booking_info_for_guest(accounts(3), None, None)[0].booking_id,
+            booking_id
+        );
+
+        // 譲渡された宿泊者がキャンセルすると、宿泊者に返金される
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(0)
+            .build());
+        contract.cancel_booking(room_id, date("1970-01-02"));
+        assert_eq!(
+            contract.get_booking(booking_id).unwrap().guest_id,
+            accounts(3)
+        );
+    }
+
+    #[test]
+    fn buy_resale_booking() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        contract.list_booking_for_resale(booking_id.clone(), U128(120));
+        assert_eq!(
+            contract.get_resale_price(booking_id.clone()),
+            Some(U128(120))
+        );
+
+        // 多く送付された分は購入者に返金される
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(150)
+            .build());
+        contract.buy_resale_booking(booking_id.clone());
+        assert_eq!(transfers(), vec![(accounts(3), 30)]);
+        assert_eq!(
+            contract.nft_token(booking_id.clone()).unwrap().owner_id,
+            accounts(3)
+        );
+        assert_eq!(contract.get_resale_price(booking_id), None);
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(1), None),
+            U128(12)
+        );
+        // 出品者はロイヤリティを差し引いた代金を出金できる
+        assert_eq!(
+            contract.get_withdrawable_balance(accounts(2), None),
+            U128(108)
+        );
+        // 宿泊料は引き続きチェックインまでコントラクトが預かる
+        assert_eq!(contract.get_escrow_balance(accounts(1), None), U128(100));
+    }
+
+    #[test]
+    fn transfer_cancels_resale_listing() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        contract.list_booking_for_resale(booking_id.clone(), U128(100));
+        allow_free_transfer(&mut context, &mut contract, &room_id);
+        contract.transfer_booking(booking_id.clone(), accounts(3));
+        assert_eq!(contract.get_resale_price(booking_id), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TRANSFER_REQUIRES_RESALE")]
+    fn transfer_booking_with_resale_policy() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        testing_env!(context.attached_deposit(1).build());
+        contract.transfer_booking(booking_id, accounts(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_TRANSFER_REQUIRES_RESALE")]
+    fn nft_transfer_with_resale_policy() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        testing_env!(context.attached_deposit(1).build());
+        contract.nft_transfer(accounts(3), booking_id, None, None);
+    }
+
+    #[test]
+    fn check_in_cancels_resale_listing() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        let storage_balance = contract.storage_balance_of(accounts(2));
+        contract.list_booking_for_resale(booking_id.clone(), U128(100));
+
+        // チェックインすると出品は取り消され、出品に使用したストレージの料金は宿泊者に戻る
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(timestamp("1970-01-02"))
+            .build());
+        contract.change_status_to_stay(room_id, date("1970-01-02"), accounts(2));
+        assert_eq!(contract.get_resale_price(booking_id), None);
+        assert_eq!(contract.storage_balance_of(accounts(2)), storage_balance);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_RESALE_PRICE_TOO_HIGH: max 100, received 120")]
+    fn buy_resale_booking_above_lowered_max_price() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        contract.list_booking_for_resale(booking_id.clone(), U128(120));
+
+        // 出品した後に、オーナーが転売価格の上限を宿泊料の100%に下げる
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_resale_policy(
+            room_id,
+            Some(ResalePolicy {
+                max_price_percent: 100,
+                royalty_percent: 10,
+            }),
+        );
+
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(120)
+            .build());
+        contract.buy_resale_booking(booking_id);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_RESALE_PRICE_TOO_HIGH: max 120, received 121")]
+    fn list_booking_above_max_price() {
+        let mut context = get_context();
+        let (mut contract, _, booking_id) = setup(&mut context);
+        contract.list_booking_for_resale(booking_id, U128(121));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_RESALE_NOT_ALLOWED")]
+    fn list_booking_without_resale_policy() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        testing_env!(context
+            .signer_account_id(accounts(1))
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_resale_policy(room_id, None);
+
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.list_booking_for_resale(booking_id, U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_TOKEN_HOLDER")]
+    fn transfer_booking_by_other_account() {
+        let mut context = get_context();
+        let (mut contract, room_id, booking_id) = setup(&mut context);
+        allow_free_transfer(&mut context, &mut contract, &room_id);
+        testing_env!(context
+            .signer_account_id(accounts(3))
+            .predecessor_account_id(accounts(3))
+            .attached_deposit(1)
+            .build());
+        contract.transfer_booking(booking_id, accounts(4));
+    }
+}
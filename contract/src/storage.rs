@@ -0,0 +1,342 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance, Promise, StorageUsage};
+
+use crate::*;
+
+// ストレージの残高を登録した時に、残高自体の保存に使用するバイト数
+// // レコードごとの追加分(40) + プレフィックス(1) + 最長のアカウントID(4 + 64) + `StorageAccount`(16 + 8)
+const STORAGE_ACCOUNT_ENTRY_BYTES: StorageUsage = 40 + 1 + 4 + 64 + 16 + 8;
+
+// アカウントがストレージの支払いに預けたNEARと、使用しているバイト数
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StorageAccount {
+    total: Balance,
+    used_bytes: StorageUsage,
+}
+
+// NEP-145: ストレージの残高
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+// NEP-145: 登録に必要な最小額と、預けられる上限（上限なし）
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+impl StorageAccount {
+    fn available(&self) -> Balance {
+        self.total.saturating_sub(storage_cost(self.used_bytes))
+    }
+
+    fn to_balance(&self) -> StorageBalance {
+        StorageBalance {
+            total: U128(self.total),
+            available: U128(self.available()),
+        }
+    }
+}
+
+fn storage_cost(bytes: StorageUsage) -> Balance {
+    bytes as Balance * env::storage_byte_cost()
+}
+
+// 部屋や予約を保存するために使用するストレージの料金は、保存したアカウントが支払う
+// // 事前に`storage_deposit`でNEARを預け、保存したバイト数に応じた金額が預けたNEARから確保される
+#[near_bindgen]
+impl Contract {
+    // ストレージの支払いに使用するNEARを預ける
+    // // `account_id`を指定した場合は、そのアカウントの残高として預ける
+    // // `registration_only`が`true`の場合は登録に必要な最小額のみを預け、残りは返金する
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+
+        if !registration_only {
+            return self.deposit_to_storage(&account_id, amount);
+        }
+        match self.storage_accounts.get(&account_id) {
+            // 登録済みの場合、`registration_only`では何も預けない
+            Some(account) => {
+                refund(amount);
+                account.to_balance()
+            }
+            None => {
+                let min = storage_cost(STORAGE_ACCOUNT_ENTRY_BYTES);
+                assert!(amount >= min, "ERR_NOT_ENOUGH_STORAGE_DEPOSIT");
+                refund(amount - min);
+                self.deposit_to_storage(&account_id, min)
+            }
+        }
+    }
+
+    // 使用していないNEARを引き出す
+    // // `amount`を指定しない場合は全額を引き出す
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let mut account = self
+            .storage_accounts
+            .get(&account_id)
+            .expect("ERR_STORAGE_NOT_REGISTERED");
+        let available = account.available();
+        let amount = amount.map_or(available, u128::from);
+        assert!(amount <= available, "ERR_NOT_ENOUGH_STORAGE_BALANCE");
+
+        account.total -= amount;
+        self.storage_accounts.insert(&account_id, &account);
+        if amount > 0 {
+            Promise::new(account_id).transfer(amount);
+        }
+        account.to_balance()
+    }
+
+    // アカウントのストレージの残高を取得する
+    // // 登録していない場合は`null`が返る
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_accounts
+            .get(&account_id)
+            .map(|account| account.to_balance())
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(storage_cost(STORAGE_ACCOUNT_ENTRY_BYTES)),
+            max: None,
+        }
+    }
+}
+
+impl Contract {
+    // アカウントのストレージの残高に`amount`を預ける
+    // // 登録していない場合は登録し、登録に必要な最小額に足りなければパニックする
+    pub(crate) fn deposit_to_storage(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) -> StorageBalance {
+        let account = match self.storage_accounts.get(account_id) {
+            Some(mut account) => {
+                account.total += amount;
+                account
+            }
+            None => {
+                let min = storage_cost(STORAGE_ACCOUNT_ENTRY_BYTES);
+                assert!(amount >= min, "ERR_NOT_ENOUGH_STORAGE_DEPOSIT");
+                StorageAccount {
+                    total: amount,
+                    used_bytes: STORAGE_ACCOUNT_ENTRY_BYTES,
+                }
+            }
+        };
+        self.storage_accounts.insert(account_id, &account);
+        account.to_balance()
+    }
+
+    // `initial_storage`からのストレージの増減を、アカウントの使用量に反映する
+    // // 増えた場合は預けたNEARが足りなければパニックし、減った場合は使用量から差し引く
+    pub(crate) fn update_storage_usage(&mut self, account_id: &AccountId, initial_storage: u64) {
+        let current_storage = env::storage_usage();
+        if current_storage > initial_storage {
+            let mut account = self
+                .storage_accounts
+                .get(account_id)
+                .expect("ERR_STORAGE_NOT_REGISTERED");
+            account.used_bytes += current_storage - initial_storage;
+            assert!(
+                storage_cost(account.used_bytes) <= account.total,
+                "ERR_NOT_ENOUGH_STORAGE_BALANCE"
+            );
+            self.storage_accounts.insert(account_id, &account);
+        } else if let Some(mut account) = self.storage_accounts.get(account_id) {
+            // 登録前に保存されたデータの分は差し引かない
+            account.used_bytes = account
+                .used_bytes
+                .saturating_sub(initial_storage - current_storage)
+                .max(STORAGE_ACCOUNT_ENTRY_BYTES);
+            self.storage_accounts.insert(account_id, &account);
+        }
+    }
+
+    // `update_storage_usage`と同じく使用量に反映するが、パニックせずに反映できたかを返す
+    // // 登録していない、または預けたNEARが足りない場合は何も変更せず`false`を返す
+    pub(crate) fn try_update_storage_usage(
+        &mut self,
+        account_id: &AccountId,
+        initial_storage: u64,
+    ) -> bool {
+        let current_storage = env::storage_usage();
+        if current_storage <= initial_storage {
+            self.update_storage_usage(account_id, initial_storage);
+            return true;
+        }
+        match self.storage_accounts.get(account_id) {
+            Some(mut account) => {
+                account.used_bytes += current_storage - initial_storage;
+                if storage_cost(account.used_bytes) > account.total {
+                    return false;
+                }
+                self.storage_accounts.insert(account_id, &account);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// 受け取ったNEARを関数をコールしたアカウントに返金する
+pub(crate) fn refund(amount: Balance) {
+    if amount > 0 {
+        Promise::new(env::predecessor_account_id()).transfer(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use near_sdk::test_utils::accounts;
+    use near_sdk::{testing_env, ONE_NEAR};
+
+    #[test]
+    fn deposit_then_withdraw() {
+        let mut context = get_context();
+        testing_env!(context.build());
+        let mut contract = Contract::new(accounts(0), Config::default());
+        let min = contract.storage_balance_bounds().min.0;
+        assert_eq!(contract.storage_balance_of(accounts(1)), None);
+
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        contract.storage_deposit(None, None);
+        assert_eq!(
+            contract.storage_balance_of(accounts(1)),
+            Some(StorageBalance {
+                total: U128(ONE_NEAR),
+                available: U128(ONE_NEAR - min),
+            })
+        );
+
+        testing_env!(context.attached_deposit(1).build());
+        let balance = contract.storage_withdraw(None);
+        assert_eq!(balance.total, U128(min));
+        assert_eq!(balance.available, U128(0));
+    }
+
+    #[test]
+    fn registration_only_refunds_the_rest() {
+        let mut context = get_context();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(accounts(0), Config::default());
+        let min = contract.storage_balance_bounds().min.0;
+
+        let balance = contract.storage_deposit(Some(accounts(2)), Some(true));
+        assert_eq!(balance.total, U128(min));
+        assert_eq!(contract.storage_balance_of(accounts(1)), None);
+    }
+
+    #[test]
+    fn room_storage_is_charged_to_owner() {
+        let mut context = get_context();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(accounts(0), Config::default());
+        contract.storage_deposit(None, None);
+        testing_env!(context.attached_deposit(0).build());
+
+        let before = contract.storage_balance_of(accounts(1)).unwrap();
+        let room_id = add_room(&mut contract, "101", ONE_NEAR);
+        let after = contract.storage_balance_of(accounts(1)).unwrap();
+        assert!(after.available.0 < before.available.0);
+
+        // 部屋を削除すると、使用していたストレージの分が戻る
+        contract.remove_room(room_id);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap(), before);
+    }
+
+    #[test]
+    fn booking_storage_is_charged_to_guest() {
+        let mut context = get_context();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(accounts(0), Config::default());
+        contract.storage_deposit(None, None);
+        contract.storage_deposit(Some(accounts(2)), None);
+        testing_env!(context.attached_deposit(0).build());
+        let room_id = add_room(&mut contract, "101", ONE_NEAR);
+
+        let owner_before = contract.storage_balance_of(accounts(1)).unwrap();
+        let guest_before = contract.storage_balance_of(accounts(2)).unwrap();
+        testing_env!(context
+            .signer_account_id(accounts(2))
+            .predecessor_account_id(accounts(2))
+            .attached_deposit(ONE_NEAR)
+            .build());
+        let check_in_date = date("1970-01-02");
+        let booking_id = contract.book_room(
+            room_id.clone(),
+            check_in_date,
+            check_in_date.add_days(1),
+            None,
+        );
+        assert!(
+            contract
+                .storage_balance_of(accounts(2))
+                .unwrap()
+                .available
+                .0
+                < guest_before.available.0
+        );
+        assert_eq!(
+            contract.storage_balance_of(accounts(1)).unwrap(),
+            owner_before
+        );
+
+        testing_env!(context.attached_deposit(0).build());
+        contract.cancel_booking(room_id, check_in_date);
+        // キャンセルした後も、予約の記録の分は宿泊者が支払う
+        // // レコードごとの追加分(40) + プレフィックス(1) + 予約のID + 予約の記録
+        let booking = contract.get_booking(booking_id.clone()).unwrap();
+        let record_bytes =
+            40 + 1 + booking_id.try_to_vec().unwrap().len() + booking.try_to_vec().unwrap().len();
+        assert_eq!(
+            contract
+                .storage_balance_of(accounts(2))
+                .unwrap()
+                .available
+                .0,
+            guest_before.available.0 - storage_cost(record_bytes as StorageUsage)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_STORAGE_NOT_REGISTERED")]
+    fn add_room_without_registration() {
+        testing_env!(get_context().build());
+        let mut contract = Contract::new(accounts(0), Config::default());
+        add_room(&mut contract, "101", ONE_NEAR);
+    }
+
+    #[test]
+    #[should_panic(expected = "ERR_NOT_ENOUGH_STORAGE_BALANCE")]
+    fn add_room_with_registration_only() {
+        let mut context = get_context();
+        testing_env!(context.attached_deposit(ONE_NEAR).build());
+        let mut contract = Contract::new(accounts(0), Config::default());
+        contract.storage_deposit(None, Some(true));
+        testing_env!(context.attached_deposit(0).build());
+        add_room(&mut contract, "101", ONE_NEAR);
+    }
+}
@@ -0,0 +1,112 @@
+// 各モジュールのテストで共通して使用する、テスト環境とデータを用意する関数
+use near_sdk::mock::VmAction;
+use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+use near_sdk::{testing_env, ONE_NEAR};
+
+use crate::booking::BookingId;
+use crate::*;
+
+// オーナーの`bob`(accounts(1))がコールするテスト環境
+pub(crate) fn get_context() -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(accounts(0))
+        .predecessor_account_id(accounts(1))
+        .signer_account_id(accounts(1));
+    builder
+}
+
+pub(crate) fn date(s: &str) -> Date {
+    s.parse().unwrap()
+}
+
+// 日付の0時0分（UTC）のタイムスタンプ（ナノ秒）
+pub(crate) fn timestamp(s: &str) -> u64 {
+    date("1970-01-01").days_until(date(s)) as u64 * 24 * 60 * 60 * 1_000_000_000
+}
+
+// 管理者を`alice`としてコントラクトを初期化する
+// // オーナーと宿泊者が部屋や予約を保存できるよう、`bob`、`charlie`、`danny`のストレージの支払いに使用するNEARを預けておく
+pub(crate) fn new_contract(context: &VMContextBuilder) -> Contract {
+    new_contract_with_config(context, Config::default())
+}
+
+pub(crate) fn new_contract_with_config(context: &VMContextBuilder, config: Config) -> Contract {
+    testing_env!(context.clone().is_view(false).build());
+    let mut contract = Contract::new(accounts(0), config);
+    testing_env!(context
+        .clone()
+        .is_view(false)
+        .attached_deposit(ONE_NEAR)
+        .build());
+    for account_id in [accounts(1), accounts(2), accounts(3)] {
+        contract.storage_deposit(Some(account_id), None);
+    }
+    testing_env!(context.build());
+    contract
+}
+
+// 関数をコールしたアカウントが、1泊`price`の部屋を登録する
+pub(crate) fn add_room(contract: &mut Contract, name: &str, price: u128) -> RoomId {
+    contract.add_room_to_owner(
+        name.to_string(),
+        "test.img".to_string(),
+        1,
+        "This is a test room.".to_string(),
+        "Tokyo".to_string(),
+        U128(price),
+        None,
+    )
+}
+
+// 関数コールがパニックすることを確認し、パニックのメッセージを返す
+// // パニックした後の状態も確認するテストで、`#[should_panic]`の代わりに使用する
+pub(crate) fn catch_panic<R>(f: impl FnOnce() -> R) -> String {
+    let err = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(_) => panic!("expected a panic"),
+        Err(err) => err,
+    };
+    match err.downcast::<String>() {
+        Ok(message) => *message,
+        Err(err) => err
+            .downcast::<&str>()
+            .map(|message| message.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+// `guest_id`が`check_in_date`から1泊、`deposit`を支払って予約する
+pub(crate) fn book(
+    context: &mut VMContextBuilder,
+    contract: &mut Contract,
+    room_id: &RoomId,
+    guest_id: AccountId,
+    check_in_date: &str,
+    deposit: u128,
+) -> BookingId {
+    testing_env!(context
+        .signer_account_id(guest_id.clone())
+        .predecessor_account_id(guest_id)
+        .attached_deposit(deposit)
+        .build());
+    let check_in_date = date(check_in_date);
+    contract.book_room(
+        room_id.clone(),
+        check_in_date,
+        check_in_date.add_days(1),
+        None,
+    )
+}
+
+// 直前の関数コールで送金されたアカウントと金額の一覧
+pub(crate) fn transfers() -> Vec<(AccountId, u128)> {
+    let mut transfers = vec![];
+    for receipt in get_created_receipts() {
+        for action in receipt.actions {
+            if let VmAction::Transfer { deposit } = action {
+                transfers.push((receipt.receiver_id.clone(), deposit));
+            }
+        }
+    }
+    transfers
+}
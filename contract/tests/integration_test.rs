@@ -1,3 +1,4 @@
+use hotel_booking::Date;
 use near_sdk::env;
 use near_sdk::json_types::U128;
 use near_sdk::test_utils::{accounts, VMContextBuilder};
@@ -8,17 +9,42 @@ fn get_context(is_view: bool) -> VMContextBuilder {
     let mut builder = VMContextBuilder::new();
     builder
         .current_account_id(accounts(0))
-        .predecessor_account_id(accounts(0))
+        .predecessor_account_id(accounts(1))
         .signer_account_id(accounts(1))
+        // 予約する日付より前の日付をブロックのタイムスタンプに設定
+        .block_timestamp(timestamp("2221-12-25"))
         // 使用するメソッドをbooleanで指定(viewメソッドはtrue, changeメソッドはfalse)
         .is_view(is_view);
     builder
 }
+fn date(s: &str) -> Date {
+    s.parse().unwrap()
+}
+// 日付の0時0分（UTC）のタイムスタンプ（ナノ秒）
+fn timestamp(s: &str) -> u64 {
+    date("1970-01-01").days_until(date(s)) as u64 * 24 * 60 * 60 * 1_000_000_000
+}
+// 管理者を`alice`としてコントラクトを初期化する
+// // オーナーと宿泊者が部屋や予約を保存できるよう、ストレージの支払いに使用するNEARを預けておく
+fn new_contract(context: &VMContextBuilder) -> hotel_booking::Contract {
+    testing_env!(context.clone().is_view(false).build());
+    let mut contract = hotel_booking::Contract::new(accounts(0), hotel_booking::Config::default());
+    testing_env!(context
+        .clone()
+        .is_view(false)
+        .attached_deposit(near_sdk::ONE_NEAR)
+        .build());
+    for account_id in [accounts(1), accounts(2)] {
+        contract.storage_deposit(Some(account_id), None);
+    }
+    testing_env!(context.build());
+    contract
+}
 #[test]
 fn add_then_get_registered_rooms() {
     let context = get_context(false);
     testing_env!(context.build());
-    let mut contract = hotel_booking::Contract::default();
+    let mut contract = new_contract(&context);
     contract.add_room_to_owner(
         "101".to_string(),
         "test.img".to_string(),
@@ -26,6 +52,7 @@ fn add_then_get_registered_rooms() {
         "This is 101 room".to_string(),
         "Tokyo".to_string(),
         U128(10),
+        None,
     );
     contract.add_room_to_owner(
         "201".to_string(),
@@ -34,11 +61,12 @@ fn add_then_get_registered_rooms() {
         "This is 201 room".to_string(),
         "Tokyo".to_string(),
         U128(10),
+        None,
     );
     // add_room_to_owner関数をコールしたアカウントIDを取得
-    let owner_id = env::signer_account_id();
+    let owner_id = env::predecessor_account_id();
 
-    let all_rooms = contract.get_rooms_registered_by_owner(owner_id);
+    let all_rooms = contract.get_rooms_registered_by_owner(owner_id, None, None);
     assert_eq!(all_rooms.len(), 2);
 }
 
@@ -46,9 +74,9 @@ fn add_then_get_registered_rooms() {
 fn no_registered_room() {
     let context = get_context(true);
     testing_env!(context.build());
-    let contract = hotel_booking::Contract::default();
+    let contract = new_contract(&context);
 
-    let no_registered_room = contract.get_rooms_registered_by_owner(accounts(0));
+    let no_registered_room = contract.get_rooms_registered_by_owner(accounts(0), None, None);
     assert_eq!(no_registered_room.len(), 0);
 }
 
@@ -57,7 +85,7 @@ fn add_then_get_available_rooms() {
     let mut context = get_context(false);
     testing_env!(context.build());
 
-    let mut contract = hotel_booking::Contract::default();
+    let mut contract = new_contract(&context);
     contract.add_room_to_owner(
         "101".to_string(),
         "test.img".to_string(),
@@ -65,6 +93,7 @@ fn add_then_get_available_rooms() {
         "This is 101 room".to_string(),
         "Tokyo".to_string(),
         U128(10),
+        None,
     );
     contract.add_room_to_owner(
         "201".to_string(),
@@ -73,11 +102,17 @@ fn add_then_get_available_rooms() {
         "This is 201 room".to_string(),
         "Tokyo".to_string(),
         U128(10),
+        None,
     );
 
     // `get_available_rooms`をコールするアカウントを設定
-    testing_env!(context.signer_account_id(accounts(2)).build());
-    let available_rooms = contract.get_available_rooms("2222-01-01".to_string());
+    testing_env!(context
+        .signer_account_id(accounts(2))
+        .predecessor_account_id(accounts(2))
+        .build());
+    let available_rooms = contract
+        .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+        .rooms;
     assert_eq!(available_rooms.len(), 2);
 }
 
@@ -85,9 +120,11 @@ fn add_then_get_available_rooms() {
 fn no_available_room() {
     let context = get_context(true);
     testing_env!(context.build());
-    let contract = hotel_booking::Contract::default();
+    let contract = new_contract(&context);
 
-    let available_rooms = contract.get_available_rooms("2222-01-01".to_string());
+    let available_rooms = contract
+        .get_available_rooms(date("2222-01-01"), date("2222-01-02"), None, None, None)
+        .rooms;
     assert_eq!(available_rooms.len(), 0);
 }
 
@@ -103,53 +140,61 @@ fn book_room_then_change_status() {
 
     testing_env!(context.build());
 
-    let owner_id = env::signer_account_id();
+    let owner_id = env::predecessor_account_id();
     // 部屋の名前
     let name = "101".to_string();
-    // 部屋のID
-    let room_id = format!("{}{}", owner_id, name);
 
-    let mut contract = hotel_booking::Contract::default();
-    contract.add_room_to_owner(
+    let mut contract = new_contract(&context);
+    // 部屋のID
+    let room_id = contract.add_room_to_owner(
         name,
         "test.img".to_string(),
         1,
         "This is 101 room".to_string(),
         "Tokyo".to_string(),
         U128(10),
+        None,
     );
 
     ///////////////////
     // CHECK BOOKING //
     ///////////////////
     // 予約を実行するアカウントを設定
-    testing_env!(context.signer_account_id(accounts(2)).build());
+    testing_env!(context
+        .signer_account_id(accounts(2))
+        .predecessor_account_id(accounts(2))
+        .build());
 
-    let check_in_date: String = "2222-01-01".to_string();
+    let check_in_date = date("2222-01-01");
+    let check_out_date = date("2222-01-02");
 
     // 予約を実行
-    contract.book_room(room_id.clone(), check_in_date.clone());
+    contract.book_room(room_id.clone(), check_in_date, check_out_date, None);
 
     // オーナー用の予約データの中身を確認
-    let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone());
+    let booked_rooms = contract.get_booking_info_for_owner(owner_id.clone(), None, None);
     assert_eq!(booked_rooms.len(), 1);
 
     // 宿泊者用の予約データの中身を確認
-    let guest_booked_rooms = contract.get_booking_info_for_guest(accounts(2));
+    let guest_booked_rooms = contract.get_booking_info_for_guest(accounts(2), None, None);
     assert_eq!(guest_booked_rooms.len(), 1);
 
     /////////////////////////
     // CHECK CHANGE STATUS //
     /////////////////////////
     // 'change_status_to_stay'をコールするアカウントを部屋のオーナーに設定
-    testing_env!(context.signer_account_id(accounts(1)).build());
+    testing_env!(context
+        .signer_account_id(accounts(1))
+        .predecessor_account_id(accounts(1))
+        .block_timestamp(timestamp("2222-01-01"))
+        .build());
 
     // 部屋のステータスを確認
     let is_available = contract.is_available(room_id.clone());
     assert!(is_available);
 
     // 部屋のステータスを変更（Available -> Stay）
-    contract.change_status_to_stay(room_id.clone(), check_in_date.clone());
+    contract.change_status_to_stay(room_id.clone(), check_in_date, accounts(2));
 
     // 再度ステータスを確認
     let is_available = contract.is_available(room_id.clone());
@@ -158,10 +203,10 @@ fn book_room_then_change_status() {
     // 部屋のステータスを変更（Stay -> Available）
     contract.change_status_to_available(room_id, check_in_date, accounts(2));
     // 予約データから削除されたかチェック
-    let booked_rooms = contract.get_booking_info_for_owner(owner_id);
+    let booked_rooms = contract.get_booking_info_for_owner(owner_id, None, None);
     assert_eq!(booked_rooms.len(), 0);
 
     // 宿泊者の予約データから消えたかチェック
-    let guest_booked_info = contract.get_booking_info_for_guest(accounts(2));
+    let guest_booked_info = contract.get_booking_info_for_guest(accounts(2), None, None);
     assert_eq!(guest_booked_info.len(), 0);
 }
@@ -13,7 +13,7 @@ const App = () => {
       <NavBar />
       <Routes>
         <Route path="/" element={<Home />} />
-        <Route path="/search/:date" element={<Search />} />
+        <Route path="/search/:checkIn/:checkOut" element={<Search />} />
         <Route path="/booked-list" element={<GuestBookedList />} />
         <Route path="/manage-rooms" element={<ManageRooms />} />
         <Route path="/manage-bookings" element={<ManageBookings />} />
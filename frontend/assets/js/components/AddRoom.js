@@ -12,6 +12,8 @@ const AddRoom = ({ save }) => {
   const [description, setDescription] = useState('');
   const [location, setLocation] = useState('');
   const [price, setPrice] = useState(0);
+  // 何日先まで予約を受け付けるか（未入力の場合はコントラクトの既定値）
+  const [bookingHorizonDays, setBookingHorizonDays] = useState('');
   // 全ての項目が入力されたか確認する
   const isFormFilled = () =>
     name && beds && image && description && location && price;
@@ -99,6 +101,18 @@ const AddRoom = ({ save }) => {
                 }}
               />
             </Form.Group>
+            {/* 予約を受け付ける期間（日） */}
+            <Form.Group className="mb-3" controlId="inputBookingHorizon">
+              <Form.Label>Booking Horizon (days)</Form.Label>
+              <Form.Control
+                type="number"
+                min={0}
+                placeholder="365"
+                onChange={(e) => {
+                  setBookingHorizonDays(e.target.value);
+                }}
+              />
+            </Form.Group>
           </Modal.Body>
         </Form>
         <Modal.Footer>
@@ -116,6 +130,7 @@ const AddRoom = ({ save }) => {
                 description,
                 location,
                 price,
+                bookingHorizonDays,
               });
               handleClose();
             }}
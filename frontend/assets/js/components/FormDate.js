@@ -7,9 +7,11 @@ import { useNavigate } from 'react-router-dom';
 
 const FormDate = () => {
   const navigate = useNavigate();
-  const [date, setDate] = useState('');
+  const [checkIn, setCheckIn] = useState('');
+  const [checkOut, setCheckOut] = useState('');
 
-  const isFormFilled = () => date;
+  // チェックアウト日はチェックイン日より後でなければならない
+  const isFormFilled = () => checkIn && checkOut && checkIn < checkOut;
   return (
     <Form>
       <Row
@@ -21,17 +23,27 @@ const FormDate = () => {
             type="date"
             htmlSize="10"
             onChange={(e) => {
-              setDate(e.target.value);
+              setCheckIn(e.target.value);
+            }}
+          />
+        </Col>
+        <Col xs="auto">
+          <Form.Control
+            type="date"
+            htmlSize="10"
+            min={checkIn}
+            onChange={(e) => {
+              setCheckOut(e.target.value);
             }}
           />
         </Col>
         <Col xs="auto">
           <Button
             variant="secondary"
-            // 検索する日付が入力されないとボタンを押せないように設定
+            // 宿泊期間が入力されないとボタンを押せないように設定
             disabled={!isFormFilled()}
-            // URLに入力された日付を入れて遷移先へ渡す
-            onClick={() => navigate(`/search/${date}`)}
+            // URLに入力された宿泊期間を入れて遷移先へ渡す
+            onClick={() => navigate(`/search/${checkIn}/${checkOut}`)}
           >
             Search
           </Button>
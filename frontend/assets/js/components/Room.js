@@ -5,14 +5,23 @@ import Col from 'react-bootstrap/Col';
 import Image from 'react-bootstrap/Image';
 import Row from 'react-bootstrap/Row';
 
-const Room = ({ room, booking }) => {
+const Room = ({ room, nights, booking }) => {
   // roomオブジェクトからデータを取得
-  const { room_id, owner_id, name, image, beds, description, location, price } =
-    room;
+  const {
+    room_id,
+    owner_id,
+    name,
+    image,
+    beds,
+    description,
+    location,
+    price,
+    total_price,
+  } = room;
 
   const handleBooking = () => {
     // `Search.js`のbookingに引数を渡す
-    booking(room_id, price);
+    booking(room_id, total_price);
   };
 
   return (
@@ -32,13 +41,14 @@ const Room = ({ room, booking }) => {
         <h6>{beds}</h6>
       </Col>
       <Col xs={3}>
-        <h6>1 night</h6>
+        <h6>{formatNearAmount(price)} NEAR / night</h6>
         <Button
           variant="outline-dark"
           disabled={!window.accountId}
           onClick={handleBooking}
         >
-          Book for {formatNearAmount(price)} NEAR
+          Book {nights} {nights === 1 ? 'night' : 'nights'} for{' '}
+          {formatNearAmount(total_price)} NEAR
         </Button>
       </Col>
     </Row>
@@ -48,6 +58,7 @@ const Room = ({ room, booking }) => {
 // 引数の型を定義
 Room.PropTypes = {
   room: PropTypes.instanceOf(Object).isRequired,
+  nights: PropTypes.number.isRequired,
   booking: PropTypes.func.isRequired,
 };
 
@@ -9,6 +9,11 @@ import getConfig from './config';
 // トランザクション実行時に使用するGASの上限を設定
 const GAS = 100000000000000;
 
+// 部屋の登録や予約の前に、ストレージの支払いとして預けるNEAR
+const STORAGE_DEPOSIT = '0.1';
+// 預けたNEARの残りがこの金額を下回ったら、追加で預ける
+const STORAGE_MIN_AVAILABLE = '0.01';
+
 const nearConfig = getConfig(process.env.NODE_ENV || 'development');
 
 // コントラクトの初期化とグローバル変数を設定
@@ -41,12 +46,18 @@ export async function initContract() {
         'get_booking_info_for_guest',
         'exists',
         'is_available',
+        'storage_balance_of',
+        'get_locations',
+        'get_rooms_by_location',
+        'get_quote',
       ],
       changeMethods: [
         'add_room_to_owner',
         'book_room',
         'change_status_to_available',
         'change_status_to_stay',
+        'cancel_booking',
+        'storage_deposit',
       ],
     },
   );
@@ -71,13 +82,42 @@ export async function accountBalance() {
 
 // コールするメソッドの処理を定義
 // // 実際に引数を渡す処理は全てここに実装
-export async function get_available_rooms(check_in_date) {
-  const availableRooms = await window.contract.get_available_rooms({
-    check_in_date,
-  });
+// `query`で場所、ベッドの数、料金、オーナーによる絞り込みと並び順を指定できる
+// // 例: `{ location: 'Tokyo', min_beds: 2, sort_by: 'newest' }`
+export async function get_available_rooms(
+  check_in_date,
+  check_out_date,
+  query = null,
+) {
+  // コントラクトは1回のコールで確認する部屋の数を制限しているため、`next_index`がなくなるまで続きを取得する
+  const availableRooms = [];
+  let from_index = 0;
+  while (from_index !== null) {
+    const page = await window.contract.get_available_rooms({
+      check_in_date,
+      check_out_date,
+      query,
+      from_index,
+    });
+    availableRooms.push(...page.rooms);
+    from_index = page.next_index;
+  }
   return availableRooms;
 }
 
+// 部屋がある場所と、場所ごとの部屋の数を取得する
+export async function get_locations() {
+  const locations = await window.contract.get_locations({});
+  return locations;
+}
+
+export async function get_rooms_by_location(location) {
+  const rooms = await window.contract.get_rooms_by_location({
+    location,
+  });
+  return rooms;
+}
+
 export async function get_rooms_registered_by_owner(owner_id) {
   const registeredRooms = await window.contract.get_rooms_registered_by_owner({
     owner_id,
@@ -118,21 +158,47 @@ export async function add_room_to_owner(room) {
   // NEAR -> yoctoNEARに変換
   room.price = parseNearAmount(room.price);
 
-  await window.contract.add_room_to_owner({
+  // 発行された部屋のIDが返る
+  const roomId = await window.contract.add_room_to_owner({
     name: room.name,
     image: room.image,
     beds: Number(room.beds),
     description: room.description,
     location: room.location,
     price: room.price,
+    booking_horizon_days: room.bookingHorizonDays
+      ? Number(room.bookingHorizonDays)
+      : null,
   });
+  return roomId;
 }
 
-export async function book_room({ room_id, date, price }) {
+// 宿泊期間の宿泊料（yoctoNEAR）の内訳を見積もる
+// // 日付や曜日によって1泊の料金が変わるため、予約する前に取得し`total`を支払う
+export async function get_quote(room_id, check_in_date, check_out_date) {
+  const quote = await window.contract.get_quote({
+    room_id,
+    check_in_date,
+    check_out_date,
+  });
+  return quote;
+}
+
+// 見積もった宿泊料`price`を支払って部屋を予約する
+// // `guests`を指定しない場合は1人として予約する
+export async function book_room({
+  room_id,
+  check_in_date,
+  check_out_date,
+  guests,
+  price,
+}) {
   await window.contract.book_room(
     {
       room_id,
-      check_in_date: date,
+      check_in_date,
+      check_out_date,
+      guests,
     },
     GAS,
     price,
@@ -151,9 +217,50 @@ export async function change_status_to_available(
   });
 }
 
-export async function change_status_to_stay(room_id, check_in_date) {
+export async function change_status_to_stay(
+  room_id,
+  check_in_date,
+  guest_id,
+) {
   await window.contract.change_status_to_stay({
     room_id,
     check_in_date,
+    guest_id,
+  });
+}
+
+export async function cancel_booking(room_id, check_in_date) {
+  await window.contract.cancel_booking({
+    room_id,
+    check_in_date,
   });
 }
+
+export async function storage_balance_of(account_id) {
+  const balance = await window.contract.storage_balance_of({
+    account_id,
+  });
+  return balance;
+}
+
+// 部屋や予約の保存に使用するストレージの料金を預ける
+// // 預けたNEARが足りない場合のみ預け、ウォレットで承認した後に元の操作をやり直す
+// // 預ける必要がない場合は`false`が返る
+export async function ensure_storage_deposit() {
+  const balance = await storage_balance_of(window.accountId);
+  if (
+    balance !== null &&
+    BigInt(balance.available) >= BigInt(parseNearAmount(STORAGE_MIN_AVAILABLE))
+  ) {
+    return false;
+  }
+  await window.contract.storage_deposit(
+    {
+      account_id: window.accountId,
+      registration_only: false,
+    },
+    GAS,
+    parseNearAmount(STORAGE_DEPOSIT),
+  );
+  return true;
+}
@@ -1,7 +1,8 @@
 import { useEffect, useState } from 'react';
 import { Table } from 'react-bootstrap';
+import Button from 'react-bootstrap/Button';
 
-import { get_booking_info_for_guest } from '../near/utils';
+import { cancel_booking, get_booking_info_for_guest } from '../near/utils';
 
 const GuestBookedList = () => {
   // 予約した部屋のデータを設定する
@@ -15,6 +16,16 @@ const GuestBookedList = () => {
     }
   };
 
+  const handleCancel = async (room_id, check_in_date) => {
+    try {
+      cancel_booking(room_id, check_in_date).then((resp) => {
+        getGuestBookedRooms();
+      });
+    } catch (error) {
+      console.log(error);
+    }
+  };
+
   useEffect(() => {
     getGuestBookedRooms();
   }, []);
@@ -38,14 +49,28 @@ const GuestBookedList = () => {
             <th scope="col">Owner</th>
             <th scope="col">Room Name</th>
             <th scope="col">Check In</th>
+            <th scope="col">Check Out</th>
+            <th scope="col">Cancel</th>
           </tr>
         </thead>
         {guestBookedRooms.map((_room) => (
-          <tbody key={_room.room_id}>
+          <tbody key={_room.room_id + _room.check_in_date}>
             <tr>
               <td>{_room.owner_id}</td>
               <td>{_room.room_name}</td>
               <td>{_room.check_in_date}</td>
+              <td>{_room.check_out_date}</td>
+              <td>
+                <Button
+                  variant="danger"
+                  size="sm"
+                  onClick={(e) =>
+                    handleCancel(_room.room_id, _room.check_in_date, e)
+                  }
+                >
+                  Cancel
+                </Button>
+              </td>
             </tr>
           </tbody>
         ))}
@@ -21,7 +21,7 @@ const ManageBookings = () => {
     }
   };
 
-  const handleCheckIn = async (room_id, check_in_date) => {
+  const handleCheckIn = async (room_id, check_in_date, guest_id) => {
     const isAvailable = await is_available(room_id);
     if (isAvailable === false) {
       // 誰かが滞在中の部屋に対して`Check In`ボタンを押すとアラートを発生させる
@@ -29,7 +29,7 @@ const ManageBookings = () => {
       return;
     }
     try {
-      change_status_to_stay(room_id, check_in_date).then((resp) => {
+      change_status_to_stay(room_id, check_in_date, guest_id).then((resp) => {
         getBookedRooms();
       });
     } catch (error) {
@@ -70,6 +70,7 @@ const ManageBookings = () => {
           <tr>
             <th scope="col">Room Name</th>
             <th scope="col">Check In</th>
+            <th scope="col">Check Out</th>
             <th scope="col">GuestID</th>
             <th scope="col">Manage Status</th>
           </tr>
@@ -79,6 +80,7 @@ const ManageBookings = () => {
             <tr>
               <td>{_room.name}</td>
               <td>{_room.check_in_date}</td>
+              <td>{_room.check_out_date}</td>
               <td>{_room.guest_id}</td>
               <td>
                 {/* ステータスが`Available`の時 */}
@@ -87,7 +89,11 @@ const ManageBookings = () => {
                     variant="success"
                     size="sm"
                     onClick={(e) =>
-                      handleCheckIn(_room.room_id, _room.check_in_date, e)
+                      handleCheckIn(
+                        _room.room_id,
+                        _room.check_in_date,
+                        _room.guest_id,
+                      )
                     }
                   >
                     Check In
@@ -7,6 +7,7 @@ import Table from 'react-bootstrap/Table';
 import AddRoom from '../components/AddRoom';
 import {
   add_room_to_owner,
+  ensure_storage_deposit,
   exists,
   get_rooms_registered_by_owner,
 } from '../near/utils';
@@ -29,6 +30,10 @@ const ManageRooms = () => {
       alert('Error: ' + data.name + ' is already registered.');
       return;
     }
+    // 部屋の保存に使用するストレージの料金を預ける
+    if (await ensure_storage_deposit()) {
+      return;
+    }
     await add_room_to_owner(data);
     getRooms();
   };
@@ -72,7 +77,7 @@ const ManageRooms = () => {
           </tr>
         </thead>
         {registeredRooms.map((_room) => (
-          <tbody key={`${_room.name}`}>
+          <tbody key={_room.room_id}>
             {/* 部屋が空室の時 */}
             {_room.status === 'Available' && (
               <tr>
@@ -4,38 +4,63 @@ import { useParams } from 'react-router-dom';
 
 import FormDate from '../components/FormDate';
 import Room from '../components/Room';
-import { book_room, get_available_rooms } from '../near/utils';
+import {
+  book_room,
+  ensure_storage_deposit,
+  get_available_rooms,
+  get_quote,
+} from '../near/utils';
 
 const Search = () => {
-  // URLから検索する日付を取得する
-  const { date } = useParams();
+  // URLから検索する宿泊期間を取得する
+  const { checkIn, checkOut } = useParams();
+  // 宿泊数を計算する
+  const nights = Math.round(
+    (Date.parse(checkOut) - Date.parse(checkIn)) / (24 * 60 * 60 * 1000),
+  );
   // 予約できる部屋のデータを設定する
   const [availableRooms, setAvailableRooms] = useState([]);
 
   const getAvailableRooms = async () => {
-    setAvailableRooms(await get_available_rooms(date));
+    const rooms = await get_available_rooms(checkIn, checkOut);
+    // 宿泊期間の宿泊料を部屋ごとに見積もる
+    const quotes = await Promise.all(
+      rooms.map((room) => get_quote(room.room_id, checkIn, checkOut)),
+    );
+    setAvailableRooms(
+      rooms.map((room, index) => ({
+        ...room,
+        total_price: quotes[index].total,
+      })),
+    );
   };
 
-  const booking = async (room_id, price) => {
+  const booking = async (room_id, total_price) => {
+    // 予約の保存に使用するストレージの料金を預ける
+    if (await ensure_storage_deposit()) {
+      return;
+    }
     book_room({
       room_id,
-      date,
-      price,
+      check_in_date: checkIn,
+      check_out_date: checkOut,
+      // 見積もった宿泊料を支払う
+      price: total_price,
     });
     getAvailableRooms();
   };
 
-  // 検索する日付が更新されるたびに`getAvailableRooms`を実行する
+  // 検索する宿泊期間が更新されるたびに`getAvailableRooms`を実行する
   useEffect(() => {
     getAvailableRooms();
-  }, [date]);
+  }, [checkIn, checkOut]);
 
   return (
     <>
       {/* 日付を入力するフォームを表示 */}
       <FormDate />
       <div className="text-center" style={{ margin: '20px' }}>
-        <h2>{date}</h2>
+        <h2>{checkIn} - {checkOut}</h2>
         {availableRooms.length === 0 ? (
           <h3>Sorry, no rooms found.</h3>
         ) : (
@@ -55,7 +80,12 @@ const Search = () => {
       {/* 予約可能な部屋を表示する */}
       <Row>
         {availableRooms.map((_room) => (
-          <Room room={{ ..._room }} key={_room.room_id} booking={booking} />
+          <Room
+            room={{ ..._room }}
+            nights={nights}
+            key={_room.room_id}
+            booking={booking}
+          />
         ))}
       </Row>
     </>